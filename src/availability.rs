@@ -0,0 +1,285 @@
+use crate::fees::PermitSource;
+use crate::{ReportDate, Trailhead};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+/// How to treat dates where upstream reports more people than the trailhead allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Overbooking {
+    /// Count overbooked trailheads as full and leave them out
+    #[default]
+    Clamp,
+    /// Keep them and show the negative number of open spots
+    Negative,
+    /// Keep them and mark them as overbooked next to the raw occupancy
+    Flag,
+}
+
+/// Open spots at one trailhead on one date. `capacity` is the limit that applies on the date,
+/// either the quota or the walk up capacity, and `availability` never goes below zero.
+#[derive(Debug, Clone, Copy)]
+pub struct AvailabilityRow<'a> {
+    pub date: NaiveDate,
+    pub trailhead: &'a Trailhead,
+    pub availability: u8,
+    pub occupancy: u8,
+    pub capacity: u8,
+}
+
+impl<'a> AvailabilityRow<'a> {
+    pub fn from_occupancy(
+        date: NaiveDate,
+        trailhead: &'a Trailhead,
+        occupancy: u8,
+        capacity: u8,
+    ) -> Self {
+        AvailabilityRow {
+            date,
+            trailhead,
+            // sometimes they are overbooked, restrict the range
+            availability: capacity - min(capacity, occupancy),
+            occupancy,
+            capacity,
+        }
+    }
+
+    /// A row with `availability` open out of the trailhead's walk up capacity.
+    #[cfg(test)]
+    pub fn new(date: NaiveDate, trailhead: &'a Trailhead, availability: u8) -> Self {
+        let occupancy = trailhead.capacity.saturating_sub(availability);
+        AvailabilityRow::from_occupancy(date, trailhead, occupancy, trailhead.capacity)
+    }
+
+    pub fn overbooked(&self) -> bool {
+        self.occupancy > self.capacity
+    }
+
+    /// `open`, `full` or `overbooked`.
+    pub fn status(&self) -> &'static str {
+        if self.overbooked() {
+            "overbooked"
+        } else if self.availability == 0 {
+            "full"
+        } else {
+            "open"
+        }
+    }
+
+    /// Occupancy as a rounded percentage of the capacity that applies on the date, over 100
+    /// when overbooked.
+    pub fn percent_full(&self) -> Option<u32> {
+        let capacity = u32::from(self.capacity);
+        let occupancy = u32::from(self.occupancy);
+        (occupancy * 100 + capacity / 2).checked_div(capacity)
+    }
+
+    /// Open spots, negative when overbooked.
+    pub fn remaining(&self) -> i16 {
+        i16::from(self.capacity) - i16::from(self.occupancy)
+    }
+
+    /// Open spots under the overbooking policy, zero for overbooked rows when clamped and
+    /// negative otherwise. Outputs, filters and sorts all read availability through here.
+    pub fn availability(&self, overbooking: Overbooking) -> i16 {
+        match overbooking {
+            Overbooking::Clamp => self.availability.into(),
+            Overbooking::Negative | Overbooking::Flag => self.remaining(),
+        }
+    }
+
+    /// Names repeat across regions (e.g. the John Muir Trail copies), so key on the id too.
+    pub fn key(&self) -> (NaiveDate, (Option<&'a str>, &'a str, &'a str)) {
+        (self.date, self.trailhead.sort_key())
+    }
+}
+
+/// Open rows ordered by date, then region name, trailhead name and id, with at most one row
+/// per key. Every output keeps this order unless asked to sort otherwise, except JSON lines,
+/// which go region by region.
+#[derive(Debug, Default, Clone)]
+pub struct AvailabilitySet<'a> {
+    rows: Vec<AvailabilityRow<'a>>,
+}
+
+impl<'a> AvailabilitySet<'a> {
+    /// Turns report occupancy into open spots for the listed trailheads, dropping full ones
+    /// unless `include_full` is set. Overbooked trailheads are kept unless they are clamped.
+    pub fn from_reports(
+        reports: impl IntoIterator<Item = ReportDate>,
+        trailheads: &'a BTreeMap<String, Trailhead>,
+        today: NaiveDate,
+        overbooking: Overbooking,
+        include_full: bool,
+    ) -> Self {
+        reports
+            .into_iter()
+            .flat_map(|report| {
+                let date = report.date;
+                report
+                    .values
+                    .into_iter()
+                    .map(move |(id, occupancy)| (date, id, occupancy))
+            })
+            .filter_map(|(date, id, occupancy)| {
+                // there are some unlisted trailheads... no name or capacity, we can ignore them
+                let trailhead = trailheads.get(id.as_str())?;
+
+                let capacity = PermitSource::for_date(date, today).capacity(trailhead);
+
+                Some(AvailabilityRow::from_occupancy(
+                    date, trailhead, occupancy, capacity,
+                ))
+            })
+            // discard full trailheads
+            .filter(|row| {
+                include_full
+                    || row.availability > 0
+                    || (row.overbooked() && overbooking != Overbooking::Clamp)
+            })
+            .collect()
+    }
+
+    pub fn rows(&self) -> &[AvailabilityRow<'a>] {
+        &self.rows
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&AvailabilityRow<'a>) -> bool) {
+        self.rows.retain(f)
+    }
+}
+
+impl<'a> FromIterator<AvailabilityRow<'a>> for AvailabilitySet<'a> {
+    /// Later rows for the same date and trailhead replace earlier ones.
+    fn from_iter<I: IntoIterator<Item = AvailabilityRow<'a>>>(iter: I) -> Self {
+        let rows = iter
+            .into_iter()
+            .map(|row| (row.key(), row))
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .collect();
+
+        AvailabilitySet { rows }
+    }
+}
+
+impl<'a> IntoIterator for AvailabilitySet<'a> {
+    type Item = AvailabilityRow<'a>;
+    type IntoIter = std::vec::IntoIter<AvailabilityRow<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, AvailabilitySet, Overbooking};
+    use crate::{ReportDate, Trailhead};
+    use chrono::NaiveDate;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn rows_from_reports() {
+        let trailheads = vec![
+            Trailhead::test("t24b", "Lyell Canyon", "tm"),
+            Trailhead::test("t21", "Cathedral Lakes", "tm"),
+        ]
+        .into_iter()
+        .map(|trailhead| (trailhead.id.clone(), trailhead))
+        .collect::<BTreeMap<_, _>>();
+
+        let today = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let walk_up = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+        let reservation = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let report = |date, values: &[(&str, u8)]| ReportDate {
+            date,
+            values: values
+                .iter()
+                .map(|&(id, occupancy)| (id.to_string(), occupancy))
+                .collect(),
+        };
+
+        let reports = || {
+            vec![
+                report(reservation, &[("t24b", 4), ("t21", 6)]),
+                report(walk_up, &[("t24b", 12), ("t21", 7), ("unlisted", 0)]),
+            ]
+        };
+        let rows = |overbooking, include_full| {
+            AvailabilitySet::from_reports(reports(), &trailheads, today, overbooking, include_full)
+                .rows()
+                .iter()
+                .map(|row| (row.date, row.trailhead.id.as_str(), row.remaining()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            rows(Overbooking::Clamp, false),
+            vec![(walk_up, "t21", 3), (reservation, "t24b", 2)]
+        );
+        assert_eq!(
+            rows(Overbooking::Negative, false),
+            vec![
+                (walk_up, "t21", 3),
+                (walk_up, "t24b", -2),
+                (reservation, "t24b", 2)
+            ]
+        );
+
+        let statuses =
+            AvailabilitySet::from_reports(reports(), &trailheads, today, Overbooking::Clamp, true);
+        let statuses = statuses
+            .rows()
+            .iter()
+            .map(|row| (row.date, row.trailhead.id.as_str(), row.status()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (walk_up, "t21", "open"),
+                (walk_up, "t24b", "overbooked"),
+                (reservation, "t21", "full"),
+                (reservation, "t24b", "open")
+            ]
+        );
+    }
+
+    #[test]
+    fn order_by_date_region_and_name() {
+        let wawona = Trailhead {
+            region: Some("ww".to_string()),
+            ..Trailhead::test("t10", "Pohono Trail", "tm")
+        };
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+
+        let rows = vec![
+            AvailabilityRow::new(second, &wawona, 1),
+            AvailabilityRow::new(first, &lyell, 1),
+            AvailabilityRow::new(first, &cathedral, 1),
+            AvailabilityRow::new(first, &wawona, 1),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+        let rows = rows
+            .rows()
+            .iter()
+            .map(|row| (row.date, row.trailhead.id.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows,
+            vec![
+                (first, "t10"),
+                (first, "t21"),
+                (first, "t24b"),
+                (second, "t10")
+            ]
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Clipboard tools to try in order, with their arguments.
+#[cfg(target_os = "macos")]
+const TOOLS: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(windows)]
+const TOOLS: &[&[&str]] = &[&["clip"]];
+#[cfg(not(any(target_os = "macos", windows)))]
+const TOOLS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Copies `text` to the system clipboard by piping it to the platform's clipboard tool.
+pub fn copy(text: &str) -> io::Result<()> {
+    for tool in TOOLS {
+        let mut child = match Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes())?;
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                tool[0], status
+            )));
+        }
+
+        return Ok(());
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no clipboard tool found",
+    ))
+}
@@ -0,0 +1,201 @@
+use crate::availability::{AvailabilityRow, Overbooking};
+use crate::fees::Cost;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Date,
+    Id,
+    Name,
+    Region,
+    Availability,
+    Occupancy,
+    Quota,
+    Capacity,
+    Cost,
+    Status,
+    PercentFull,
+    Limit,
+}
+
+impl Column {
+    const ALL: [(&'static str, Column); 12] = [
+        ("date", Column::Date),
+        ("id", Column::Id),
+        ("name", Column::Name),
+        ("region", Column::Region),
+        ("availability", Column::Availability),
+        ("occupancy", Column::Occupancy),
+        ("quota", Column::Quota),
+        ("capacity", Column::Capacity),
+        ("cost", Column::Cost),
+        ("status", Column::Status),
+        ("percent_full", Column::PercentFull),
+        ("limit", Column::Limit),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Column> {
+        Column::ALL
+            .iter()
+            .find(|(known, _)| name.eq_ignore_ascii_case(known))
+            .map(|&(_, column)| column)
+    }
+
+    pub fn parse(name: &str) -> Result<Column, String> {
+        Column::from_name(name).ok_or_else(|| {
+            let known = Column::ALL.iter().map(|(known, _)| *known);
+            format!(
+                "unknown column {:?}, expected one of {}",
+                name,
+                known.collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        Column::ALL
+            .iter()
+            .find(|&&(_, column)| column == self)
+            .map(|&(name, _)| name)
+            .unwrap_or_default()
+    }
+
+    pub fn value(
+        self,
+        row: &AvailabilityRow,
+        region_names: bool,
+        overbooking: Overbooking,
+        cost: Option<Cost>,
+    ) -> String {
+        let trailhead = row.trailhead;
+        match self {
+            Column::Availability if row.overbooked() && overbooking == Overbooking::Flag => {
+                "overbooked".to_string()
+            }
+            Column::Date => row.date.to_string(),
+            Column::Id => trailhead.id.clone(),
+            Column::Name => crate::display_name(trailhead, region_names),
+            Column::Region => trailhead.region_name().unwrap_or_default().to_string(),
+            Column::Availability => row.availability(overbooking).to_string(),
+            Column::Occupancy => row.occupancy.to_string(),
+            Column::Quota => trailhead.quota.to_string(),
+            Column::Capacity => trailhead.capacity.to_string(),
+            Column::Cost => cost.map(|cost| cost.to_string()).unwrap_or_default(),
+            Column::Status => row.status().to_string(),
+            Column::PercentFull => row
+                .percent_full()
+                .map(|percent| percent.to_string())
+                .unwrap_or_default(),
+            Column::Limit => row.capacity.to_string(),
+        }
+    }
+
+    /// Like `value`, but counts are JSON numbers and a missing cost is null. Availability is
+    /// always a number, negative when overbooked unless clamped.
+    pub fn json(
+        self,
+        row: &AvailabilityRow,
+        region_names: bool,
+        overbooking: Overbooking,
+        cost: Option<Cost>,
+    ) -> serde_json::Value {
+        match self {
+            Column::Availability => row.availability(overbooking).into(),
+            Column::Occupancy => row.occupancy.into(),
+            Column::Quota => row.trailhead.quota.into(),
+            Column::Capacity => row.trailhead.capacity.into(),
+            Column::Cost if cost.is_none() => serde_json::Value::Null,
+            Column::PercentFull => serde_json::json!(row.percent_full()),
+            Column::Limit => row.capacity.into(),
+            column => column.value(row, region_names, overbooking, cost).into(),
+        }
+    }
+
+    /// Stable sort of rows by this column, smallest first. Cost is not known here and leaves the
+    /// order unchanged.
+    pub fn sort(self, rows: &mut [AvailabilityRow], overbooking: Overbooking) {
+        match self {
+            Column::Date => rows.sort_by_key(|row| row.date),
+            Column::Id => rows.sort_by_key(|row| row.trailhead.id.as_str()),
+            Column::Name => rows.sort_by_key(|row| row.trailhead.name.as_str()),
+            Column::Region => rows.sort_by_key(|row| row.trailhead.region_name()),
+            Column::Availability => rows.sort_by_key(|row| row.availability(overbooking)),
+            Column::Occupancy => rows.sort_by_key(|row| row.occupancy),
+            Column::Quota => rows.sort_by_key(|row| row.trailhead.quota),
+            Column::Capacity => rows.sort_by_key(|row| row.trailhead.capacity),
+            Column::Cost => {}
+            Column::Status => rows.sort_by_key(|row| row.status()),
+            Column::PercentFull => rows.sort_by_key(|row| row.percent_full()),
+            Column::Limit => rows.sort_by_key(|row| row.capacity),
+        }
+    }
+}
+
+/// Sorts the rows with `sort`, largest first when `desc`. Reversing around a stable sort keeps
+/// tied rows in the same order either way.
+pub fn sort_ordered<T>(rows: &mut [T], desc: bool, sort: impl FnOnce(&mut [T])) {
+    if desc {
+        rows.reverse();
+    }
+    sort(rows);
+    if desc {
+        rows.reverse();
+    }
+}
+
+/// An output column and the header it is printed under.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSpec {
+    pub column: Column,
+    pub header: String,
+}
+
+impl From<Column> for ColumnSpec {
+    fn from(column: Column) -> ColumnSpec {
+        ColumnSpec {
+            column,
+            header: column.name().to_string(),
+        }
+    }
+}
+
+impl ColumnSpec {
+    /// Parses `column` or `column=header`.
+    pub fn parse(s: &str) -> Result<ColumnSpec, String> {
+        let (name, header) = s.split_once('=').unwrap_or((s, s));
+        let column = Column::parse(name)?;
+
+        if header.is_empty() {
+            return Err(format!("column {:?} has an empty header", name));
+        }
+
+        Ok(ColumnSpec {
+            column,
+            header: header.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::columns::{Column, ColumnSpec};
+
+    #[test]
+    fn parse_columns() {
+        assert_eq!(
+            ColumnSpec::parse("availability"),
+            Ok(ColumnSpec {
+                column: Column::Availability,
+                header: "availability".to_string(),
+            })
+        );
+        assert_eq!(
+            ColumnSpec::parse("Name=trailhead"),
+            Ok(ColumnSpec {
+                column: Column::Name,
+                header: "trailhead".to_string(),
+            })
+        );
+        assert!(ColumnSpec::parse("elevation").is_err());
+        assert!(ColumnSpec::parse("date=").is_err());
+    }
+}
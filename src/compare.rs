@@ -0,0 +1,232 @@
+use crate::availability::AvailabilityRow;
+use crate::Selection;
+use crate::Trailhead;
+use chrono::{Datelike, NaiveDate};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Compare availability across two candidate trip windows
+#[derive(Debug, Args)]
+pub struct Compare {
+    /// First window, e.g. jun-10..jun-20 or 2021-06-10..2021-06-20
+    #[arg(long, env = "YOSE_WINDOW_A", value_parser = parse_window)]
+    pub window_a: DateWindow,
+
+    /// Second window, in the same format
+    #[arg(long, env = "YOSE_WINDOW_B", value_parser = parse_window)]
+    pub window_b: DateWindow,
+
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+/// An inclusive range of dates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateWindow {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateWindow {
+    /// Parses `start..end`. Dates without a year (`jun-10`) resolve to their next occurrence
+    /// on or after `today`, and an end before the start rolls into the following year.
+    pub fn parse(s: &str, today: NaiveDate) -> Result<DateWindow, String> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| format!("expected START..END, got {:?}", s))?;
+
+        let start = parse_date(start, today)?;
+        let end = parse_date(end, start)?;
+
+        if end < start {
+            return Err(format!("window {:?} ends before it starts", s));
+        }
+
+        Ok(DateWindow { start, end })
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+
+fn parse_window(s: &str) -> Result<DateWindow, String> {
+    DateWindow::parse(s, crate::today())
+}
+
+/// Parses `2021-06-10`, or `jun-10` as its next occurrence on or after `after`.
+pub fn parse_date(s: &str, after: NaiveDate) -> Result<NaiveDate, String> {
+    match NaiveDate::from_str(s) {
+        Ok(date) => Ok(date),
+        Err(_) => {
+            let (month, day) = parse_month_day(s)?;
+            next_occurrence(month, day, after)
+        }
+    }
+}
+
+fn parse_month_day(s: &str) -> Result<(u32, u32), String> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let invalid = || format!("expected a date like jun-10 or 2021-06-10, got {:?}", s);
+    let (month, day) = s.split_once('-').ok_or_else(invalid)?;
+    let month = MONTHS
+        .iter()
+        .position(|name| month.eq_ignore_ascii_case(name))
+        .ok_or_else(invalid)?;
+    let day = day.parse().map_err(|_| invalid())?;
+
+    Ok((month as u32 + 1, day))
+}
+
+fn next_occurrence(month: u32, day: u32, after: NaiveDate) -> Result<NaiveDate, String> {
+    let date = |year| {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .ok_or_else(|| format!("{}-{} is not a valid date", month, day))
+    };
+
+    let candidate = date(after.year())?;
+    if candidate < after {
+        date(after.year() + 1)
+    } else {
+        Ok(candidate)
+    }
+}
+
+/// Open days and the most spots on any one day, per window.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WindowStats {
+    pub open_days: u32,
+    pub max_availability: u8,
+}
+
+impl WindowStats {
+    fn add(&mut self, availability: u8) {
+        self.open_days += 1;
+        self.max_availability = self.max_availability.max(availability);
+    }
+}
+
+/// A trailhead with its stats in the first and second window.
+pub type Comparison<'a> = (&'a Trailhead, WindowStats, WindowStats);
+
+/// Side-by-side stats for every trailhead open in either window, keyed by region name, then
+/// name and id.
+pub fn compare<'a>(
+    compare: &Compare,
+    rows: impl IntoIterator<Item = AvailabilityRow<'a>>,
+) -> BTreeMap<(Option<&'a str>, &'a str, &'a str), Comparison<'a>> {
+    let mut result = BTreeMap::new();
+
+    for row in rows {
+        if row.availability == 0 {
+            continue;
+        }
+
+        let in_a = compare.window_a.contains(row.date);
+        let in_b = compare.window_b.contains(row.date);
+        if !in_a && !in_b {
+            continue;
+        }
+
+        let (_, a, b) = result.entry(row.trailhead.sort_key()).or_insert((
+            row.trailhead,
+            WindowStats::default(),
+            WindowStats::default(),
+        ));
+
+        if in_a {
+            a.add(row.availability);
+        }
+        if in_b {
+            b.add(row.availability);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::compare::{compare, Compare, DateWindow, WindowStats};
+    use crate::{Selection, Trailhead};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parse_windows() {
+        let today = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+
+        assert_eq!(
+            DateWindow::parse("sep-20..Sep-25", today),
+            Ok(DateWindow {
+                start: NaiveDate::from_ymd_opt(2020, 9, 20).unwrap(),
+                end: NaiveDate::from_ymd_opt(2020, 9, 25).unwrap(),
+            })
+        );
+        assert_eq!(
+            DateWindow::parse("jun-10..jun-20", today),
+            Ok(DateWindow {
+                start: NaiveDate::from_ymd_opt(2021, 6, 10).unwrap(),
+                end: NaiveDate::from_ymd_opt(2021, 6, 20).unwrap(),
+            })
+        );
+        assert_eq!(
+            DateWindow::parse("dec-28..jan-3", today).map(|window| window.end),
+            Ok(NaiveDate::from_ymd_opt(2021, 1, 3).unwrap())
+        );
+        assert_eq!(
+            DateWindow::parse("2020-10-01..2020-10-05", today).map(|window| window.start),
+            Ok(NaiveDate::from_ymd_opt(2020, 10, 1).unwrap())
+        );
+        assert!(DateWindow::parse("2020-10-05..2020-10-01", today).is_err());
+        assert!(DateWindow::parse("jun-10", today).is_err());
+        assert!(DateWindow::parse("juin-10..jun-20", today).is_err());
+    }
+
+    #[test]
+    fn compare_windows() {
+        let lyell = Trailhead {
+            quota: 21,
+            capacity: 35,
+            ..Trailhead::test("t24b", "Lyell Canyon", "tm")
+        };
+        let windows = Compare {
+            window_a: DateWindow {
+                start: NaiveDate::from_ymd_opt(2020, 9, 10).unwrap(),
+                end: NaiveDate::from_ymd_opt(2020, 9, 12).unwrap(),
+            },
+            window_b: DateWindow {
+                start: NaiveDate::from_ymd_opt(2020, 9, 20).unwrap(),
+                end: NaiveDate::from_ymd_opt(2020, 9, 22).unwrap(),
+            },
+            selection: Selection::default(),
+        };
+        let rows = vec![
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 10).unwrap(), &lyell, 2),
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 11).unwrap(), &lyell, 5),
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 15).unwrap(), &lyell, 9),
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 21).unwrap(), &lyell, 1),
+        ];
+
+        let result = compare(&windows, rows);
+        let (_, a, b) = result[&(Some("Tuolumne Meadows"), "Lyell Canyon", "t24b")];
+        assert_eq!(
+            a,
+            WindowStats {
+                open_days: 2,
+                max_availability: 5
+            }
+        );
+        assert_eq!(
+            b,
+            WindowStats {
+                open_days: 1,
+                max_availability: 1
+            }
+        );
+    }
+}
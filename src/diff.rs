@@ -0,0 +1,258 @@
+use crate::availability::{AvailabilityRow, AvailabilitySet};
+use crate::store::Store;
+use crate::Selection;
+use clap::Args;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+/// Show which trailheads gained or lost spots between two stored snapshots
+#[derive(Debug, Args)]
+pub struct Diff {
+    /// Snapshot id to compare against, the one recorded before --after by default
+    #[arg(long, env = "YOSE_DIFF_BEFORE")]
+    pub before: Option<i64>,
+
+    /// Snapshot id to compare, the latest by default
+    #[arg(long, env = "YOSE_DIFF_AFTER")]
+    pub after: Option<i64>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+impl Diff {
+    /// The snapshot ids to compare, before and after.
+    pub fn snapshots(&self, store: &Store) -> Result<(i64, i64), Box<dyn Error>> {
+        let ids = store.ids()?;
+        let after = match self.after {
+            Some(after) => after,
+            None => *ids.last().ok_or("no snapshots in the store")?,
+        };
+        let before = match self.before {
+            Some(before) => before,
+            None => {
+                let position = ids
+                    .iter()
+                    .position(|&id| id == after)
+                    .ok_or_else(|| format!("no snapshot {} in the store", after))?;
+                let previous = position
+                    .checked_sub(1)
+                    .and_then(|previous| ids.get(previous));
+                *previous.ok_or_else(|| format!("no snapshot recorded before {}", after))?
+            }
+        };
+
+        Ok((before, after))
+    }
+}
+
+/// Compare live availability with a snapshot another instance published, to spot a session
+/// that is being served stale or filtered data
+#[derive(Debug, Args)]
+pub struct Baseline {
+    /// URL of the document published with --format json
+    pub url: String,
+
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+/// The same trailhead and date in both snapshots, with a different number of open spots.
+#[derive(Debug, Clone, Copy)]
+pub struct Change<'a> {
+    pub before: AvailabilityRow<'a>,
+    pub after: AvailabilityRow<'a>,
+}
+
+/// What a change means for someone looking for a permit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// Full before, open now
+    BecameAvailable,
+    /// Open before, full now
+    BecameFull,
+    Increased,
+    Decreased,
+}
+
+impl Transition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transition::BecameAvailable => "became-available",
+            Transition::BecameFull => "became-full",
+            Transition::Increased => "increased",
+            Transition::Decreased => "decreased",
+        }
+    }
+}
+
+impl Change<'_> {
+    /// Spots gained, negative when spots were taken.
+    pub fn delta(&self) -> i16 {
+        i16::from(self.after.availability) - i16::from(self.before.availability)
+    }
+
+    /// Going to or from zero spots counts before the direction of the change.
+    pub fn transition(&self) -> Transition {
+        match (self.before.availability, self.after.availability) {
+            (0, _) => Transition::BecameAvailable,
+            (_, 0) => Transition::BecameFull,
+            _ if self.delta() > 0 => Transition::Increased,
+            _ => Transition::Decreased,
+        }
+    }
+}
+
+/// Changes for every trailhead and date in both snapshots, ordered like the rows. Dates and
+/// regions only in one snapshot were not fetched in the other and are left out.
+pub fn changes<'a>(before: &AvailabilitySet<'a>, after: &AvailabilitySet<'a>) -> Vec<Change<'a>> {
+    pair_up(before, after, false)
+}
+
+/// Changes from a published document to live availability. Published documents leave out full
+/// trailheads, so a trailhead missing on a date both sides have counts as full.
+pub fn changes_from_baseline<'a>(
+    baseline: &AvailabilitySet<'a>,
+    live: &AvailabilitySet<'a>,
+) -> Vec<Change<'a>> {
+    pair_up(baseline, live, true)
+}
+
+fn pair_up<'a>(
+    before: &AvailabilitySet<'a>,
+    after: &AvailabilitySet<'a>,
+    missing_is_full: bool,
+) -> Vec<Change<'a>> {
+    let mut pairs = BTreeMap::<_, (Option<AvailabilityRow>, Option<AvailabilityRow>)>::new();
+    for row in before.rows() {
+        pairs.entry(row.key()).or_default().0 = Some(*row);
+    }
+    for row in after.rows() {
+        pairs.entry(row.key()).or_default().1 = Some(*row);
+    }
+
+    let dates = |rows: &AvailabilitySet| rows.rows().iter().map(|row| row.date).collect();
+    let before_dates: BTreeSet<_> = dates(before);
+    let after_dates: BTreeSet<_> = dates(after);
+    let on_both = |row: &AvailabilityRow| {
+        missing_is_full && before_dates.contains(&row.date) && after_dates.contains(&row.date)
+    };
+    let full = |row: AvailabilityRow<'a>| AvailabilityRow {
+        availability: 0,
+        occupancy: row.capacity,
+        ..row
+    };
+
+    pairs
+        .into_values()
+        .filter_map(|pair| {
+            let change = match pair {
+                (Some(before), Some(after)) => Change { before, after },
+                (Some(before), None) if on_both(&before) => Change {
+                    before,
+                    after: full(before),
+                },
+                (None, Some(after)) if on_both(&after) => Change {
+                    before: full(after),
+                    after,
+                },
+                _ => return None,
+            };
+            if change.delta() == 0 {
+                None
+            } else {
+                Some(change)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, AvailabilitySet};
+    use crate::diff::{changes, changes_from_baseline, Transition};
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn gained_and_lost() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+
+        let before = vec![
+            AvailabilityRow::new(first, &lyell, 0),
+            AvailabilityRow::new(first, &cathedral, 3),
+            AvailabilityRow::new(second, &lyell, 5),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+        let after = vec![
+            AvailabilityRow::new(first, &lyell, 2),
+            AvailabilityRow::new(first, &cathedral, 3),
+            AvailabilityRow::new(second, &lyell, 1),
+            AvailabilityRow::new(second, &cathedral, 4),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+
+        let result = changes(&before, &after)
+            .iter()
+            .map(|change| {
+                (
+                    change.after.date,
+                    change.after.trailhead.id.as_str(),
+                    change.delta(),
+                    change.transition(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            vec![
+                (first, "t24b", 2, Transition::BecameAvailable),
+                (second, "t24b", -4, Transition::Decreased)
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_from_baseline_is_full() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+
+        let baseline = vec![
+            AvailabilityRow::new(first, &lyell, 2),
+            AvailabilityRow::new(first, &cathedral, 3),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+        let live = vec![
+            AvailabilityRow::new(first, &lyell, 4),
+            AvailabilityRow::new(second, &lyell, 1),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+
+        let result = changes_from_baseline(&baseline, &live)
+            .iter()
+            .map(|change| {
+                (
+                    change.after.trailhead.id.as_str(),
+                    change.before.availability,
+                    change.after.availability,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(result, vec![("t21", 3, 0), ("t24b", 2, 4)]);
+        let transitions = changes_from_baseline(&baseline, &live)
+            .iter()
+            .map(|change| change.transition().name())
+            .collect::<Vec<_>>();
+        assert_eq!(transitions, vec!["became-full", "increased"]);
+        assert_eq!(changes(&baseline, &live).len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+use crate::availability::AvailabilitySet;
+use crate::matrix::Matrix;
+use crate::output::SCHEMA_VERSION;
+use crate::Selection;
+use arrow_array::{
+    ArrayRef, Date32Array, Int16Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+    UInt8Array,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, ValueEnum};
+use minisign::{PError, SecretKey};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::metadata::KeyValue;
+use rust_xlsxwriter::{ConditionalFormat3ColorScale, DocProperties, Format, Workbook, XlsxError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Write the open trailheads to a file
+#[derive(Debug, Args)]
+pub struct Export {
+    #[arg(value_enum)]
+    pub format: ExportFormat,
+
+    /// File to write
+    #[arg(long, short, env = "YOSE_EXPORT_OUTPUT")]
+    pub output: PathBuf,
+
+    /// minisign secret key to sign the file with, the signature is written next to it
+    #[arg(long, env = "YOSE_SIGN_KEY")]
+    pub sign_key: Option<PathBuf>,
+
+    /// Password for the signing key, prompted for when not given
+    #[arg(
+        long,
+        env = "YOSE_SIGN_PASSWORD",
+        hide_env_values = true,
+        requires = "sign_key"
+    )]
+    pub sign_password: Option<String>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// Excel workbook with one sheet per region, dates as rows and trailheads as columns
+    Xlsx,
+    /// Parquet file with one record per trailhead and date, stamped with the fetch time
+    Parquet,
+}
+
+/// Where an export came from. It is embedded in the file and in the signature's trusted
+/// comment.
+#[derive(Debug, Clone, Copy)]
+pub struct Provenance<'a> {
+    pub source: &'a str,
+    pub fetched: DateTime<Utc>,
+}
+
+impl Provenance<'_> {
+    fn entries(&self) -> [(&'static str, String); 4] {
+        [
+            ("yose.version", env!("CARGO_PKG_VERSION").to_string()),
+            ("yose.schema_version", SCHEMA_VERSION.to_string()),
+            ("yose.source", self.source.to_string()),
+            ("yose.fetched", self.fetched.to_rfc3339()),
+        ]
+    }
+}
+
+impl fmt::Display for Provenance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.entries();
+        let entries = entries
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value));
+        write!(f, "{}", entries.collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// Writes a workbook with a sheet per region. Blank cells are trailheads with nothing open, and
+/// the open counts are shaded from red to green.
+pub fn xlsx(
+    matrices: &BTreeMap<&str, Matrix>,
+    region_names: bool,
+    provenance: Provenance,
+    output: &Path,
+) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+    workbook.set_properties(&DocProperties::new().set_comment(provenance.to_string()));
+    let bold = Format::new().set_bold();
+
+    for (region, matrix) in matrices {
+        let sheet = workbook.add_worksheet();
+        // sheet names are limited to 31 characters
+        sheet.set_name(region.chars().take(31).collect::<String>())?;
+
+        sheet.write_string_with_format(0, 0, "Date", &bold)?;
+        sheet.set_column_width(0, 12)?;
+        for (column, trailhead) in (1..).zip(&matrix.trailheads) {
+            let name = crate::display_name(trailhead, region_names);
+            sheet.write_string_with_format(0, column, name, &bold)?;
+        }
+
+        for (row, (date, values)) in (1..).zip(&matrix.dates) {
+            sheet.write_string(row, 0, date.to_string())?;
+            for (column, value) in (1..).zip(values) {
+                if let Some(availability) = value {
+                    sheet.write_number(row, column, *availability)?;
+                }
+            }
+        }
+
+        sheet.set_freeze_panes(1, 1)?;
+        if !matrix.dates.is_empty() && !matrix.trailheads.is_empty() {
+            sheet.add_conditional_format(
+                1,
+                1,
+                matrix.dates.len() as u32,
+                matrix.trailheads.len() as u16,
+                &ConditionalFormat3ColorScale::new(),
+            )?;
+        }
+    }
+
+    workbook.save(output)
+}
+
+/// Writes one record per row with the trailhead's limits and the time the reports were
+/// fetched, so snapshots from different runs can be stacked.
+pub fn parquet(
+    rows: &AvailabilitySet,
+    region_names: bool,
+    provenance: Provenance,
+    output: &Path,
+) -> Result<(), ParquetError> {
+    let rows = rows.rows();
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap_or_default();
+    let utc = Some(Arc::from("UTC"));
+
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("date", DataType::Date32, false),
+        Field::new("id", DataType::Utf8, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("region", DataType::Utf8, true),
+        Field::new("quota", DataType::UInt8, false),
+        Field::new("capacity", DataType::UInt8, false),
+        Field::new("occupancy", DataType::UInt8, false),
+        Field::new("availability", DataType::Int16, false),
+        Field::new(
+            "snapshot",
+            DataType::Timestamp(TimeUnit::Microsecond, utc.clone()),
+            false,
+        ),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Date32Array::from_iter_values(
+            rows.iter().map(|row| (row.date - epoch).num_days() as i32),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.trailhead.id.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter()
+                .map(|row| crate::display_name(row.trailhead, region_names)),
+        )),
+        Arc::new(StringArray::from(
+            rows.iter()
+                .map(|row| row.trailhead.region_name())
+                .collect::<Vec<_>>(),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|row| row.trailhead.quota),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|row| row.trailhead.capacity),
+        )),
+        Arc::new(UInt8Array::from_iter_values(
+            rows.iter().map(|row| row.occupancy),
+        )),
+        Arc::new(Int16Array::from_iter_values(
+            rows.iter().map(|row| row.remaining()),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from(vec![
+                provenance.fetched.timestamp_micros();
+                rows.len()
+            ])
+            .with_timezone_opt(utc),
+        ),
+    ];
+
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+    let mut writer = ArrowWriter::try_new(File::create(output)?, schema, None)?;
+    for (key, value) in provenance.entries().iter() {
+        writer.append_key_value_metadata(KeyValue::new(key.to_string(), value.clone()));
+    }
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Signs `output` into `output.minisig`, with the provenance and file name as the trusted
+/// comment. Without a password the key's password is prompted for.
+pub fn sign(
+    output: &Path,
+    key: &Path,
+    password: Option<String>,
+    provenance: Provenance,
+) -> Result<(), PError> {
+    let secret_key = SecretKey::from_file(key, password)?;
+    let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+    let trusted_comment = format!("{} file={}", provenance, file_name);
+
+    let signature = minisign::sign(
+        None,
+        &secret_key,
+        File::open(output)?,
+        Some(&trusted_comment),
+        Some("signed by yose"),
+    )?;
+
+    let mut path = output.as_os_str().to_owned();
+    path.push(".minisig");
+    fs::write(path, signature.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, AvailabilitySet};
+    use crate::Trailhead;
+    use arrow_array::{Array, Int16Array, StringArray};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::{self, File};
+
+    #[test]
+    fn parquet_round_trip() {
+        let trailhead = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let rows = vec![AvailabilityRow::new(date, &trailhead, 4)]
+            .into_iter()
+            .collect::<AvailabilitySet>();
+        let provenance = super::Provenance {
+            source: "https://example.com/query.php",
+            fetched: Utc.with_ymd_and_hms(2020, 9, 10, 7, 0, 0).unwrap(),
+        };
+
+        let path = std::env::temp_dir().join(format!("yose-{}.parquet", std::process::id()));
+        super::parquet(&rows, false, provenance, &path).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let metadata = builder.metadata().file_metadata().key_value_metadata();
+        let fetched = metadata
+            .and_then(|metadata| metadata.iter().find(|kv| kv.key == "yose.fetched"))
+            .and_then(|kv| kv.value.clone());
+        assert_eq!(fetched.as_deref(), Some("2020-09-10T07:00:00+00:00"));
+        let mut reader = builder.build().unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.num_columns(), 9);
+        let column = |name| batch.column_by_name(name).unwrap().as_any();
+        let id = column("id").downcast_ref::<StringArray>().unwrap();
+        assert_eq!(id.value(0), "t24b");
+        let availability = column("availability").downcast_ref::<Int16Array>().unwrap();
+        assert_eq!(availability.value(0), 4);
+    }
+}
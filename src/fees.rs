@@ -0,0 +1,145 @@
+use crate::Trailhead;
+use chrono::NaiveDate;
+use std::fmt;
+
+/// How a slot on a date is obtained: advance reservations draw from the trailhead quota, the
+/// walk up period opens the full capacity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermitSource {
+    Reservation,
+    WalkUp,
+}
+
+impl PermitSource {
+    pub fn for_date(date: NaiveDate, today: NaiveDate) -> PermitSource {
+        // the walk up period in 2020 opens the remaining capacity 15 days out
+        if date.signed_duration_since(today).num_days() > 15 {
+            PermitSource::Reservation
+        } else {
+            PermitSource::WalkUp
+        }
+    }
+
+    pub fn capacity(self, trailhead: &Trailhead) -> u8 {
+        match self {
+            PermitSource::Reservation => trailhead.quota,
+            PermitSource::WalkUp => trailhead.capacity,
+        }
+    }
+
+    /// Fee rules for this source, in cents, as the park published them for 2020
+    /// (https://www.nps.gov/yose/planyourvisit/wildpermits.htm): $5 per reservation and $5 per
+    /// person, plus $10 per person for Half Dome. Walk up permits carry no reservation fee.
+    pub fn fees(self) -> Fees {
+        match self {
+            PermitSource::Reservation => Fees {
+                reservation: 500,
+                per_person: 500,
+                half_dome_per_person: 1000,
+            },
+            PermitSource::WalkUp => Fees {
+                reservation: 0,
+                per_person: 500,
+                half_dome_per_person: 1000,
+            },
+        }
+    }
+}
+
+/// Trailheads whose permits can add the Half Dome Cables, by id in every region they are
+/// listed in: those the site marks as not reflecting Half Dome Cables availability (Happy Isles
+/// and Glacier Point to Little Yosemite Valley, and the Sunrise/Merced Lake pass through), their
+/// John Muir Trail listings, and Sunrise Lakes, which the park's 2020 Half Dome permit page lists
+/// as a wilderness trailhead for the cables.
+const HALF_DOME_TRAILHEADS: &[&str] = &[
+    "y01a", "y01b", "j01a", "j01b", "y03a", "j03a", "w03a", "t19", "j19",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fees {
+    pub reservation: u32,
+    pub per_person: u32,
+    pub half_dome_per_person: u32,
+}
+
+impl Fees {
+    /// Estimated total for a group. The Half Dome add-on only applies to trailheads that can
+    /// include the cables on the same permit.
+    pub fn estimate(&self, trailhead: &Trailhead, group_size: u8, half_dome: bool) -> Cost {
+        let mut per_person = self.per_person;
+        if half_dome && HALF_DOME_TRAILHEADS.contains(&trailhead.id.as_str()) {
+            per_person += self.half_dome_per_person;
+        }
+
+        Cost(self.reservation + per_person * u32::from(group_size))
+    }
+}
+
+/// An amount in US cents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cost(pub u32);
+
+impl fmt::Display for Cost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.0 / 100, self.0 % 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fees::{Cost, PermitSource};
+    use crate::tests::TRAILHEADS;
+    use crate::{Response, Trailhead, Trailheads};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn source_follows_walk_up_period() {
+        let today = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        assert_eq!(
+            PermitSource::for_date(NaiveDate::from_ymd_opt(2020, 9, 25).unwrap(), today),
+            PermitSource::WalkUp
+        );
+        assert_eq!(
+            PermitSource::for_date(NaiveDate::from_ymd_opt(2020, 9, 26).unwrap(), today),
+            PermitSource::Reservation
+        );
+    }
+
+    #[test]
+    fn estimate_group_cost() {
+        let half_dome = Trailhead::test("y01b", "Happy Isles to Little Yosemite Valley", "yv");
+        let plain = Trailhead::test("y02", "Mirror Lake to Snow Creek", "yv");
+        let fees = PermitSource::Reservation.fees();
+
+        assert_eq!(fees.estimate(&plain, 4, false), Cost(2500));
+        assert_eq!(fees.estimate(&plain, 4, true), Cost(2500));
+        assert_eq!(fees.estimate(&half_dome, 4, true), Cost(6500));
+        assert_eq!(
+            PermitSource::WalkUp.fees().estimate(&plain, 2, false),
+            Cost(1000)
+        );
+        assert_eq!(Cost(6500).to_string(), "65.00");
+    }
+
+    #[test]
+    fn half_dome_from_little_yosemite_valley_and_sunrise() {
+        let trailheads = serde_json::from_str::<Response<Trailheads>>(TRAILHEADS)
+            .unwrap()
+            .response
+            .values;
+        let fees = PermitSource::Reservation.fees();
+
+        for trailhead in trailheads.values() {
+            let name = &trailhead.name;
+            let corridor = name.contains("Little Yosemite Valley") || name.contains("Sunrise");
+            let disclaimer = trailhead
+                .description
+                .as_deref()
+                .is_some_and(|description| description.contains("Half Dome Cables"));
+            let added = fees.estimate(trailhead, 1, true) != fees.estimate(trailhead, 1, false);
+
+            assert_eq!(added, corridor, "{} {}", trailhead.id, name);
+            assert!(!disclaimer || added, "{} {}", trailhead.id, name);
+        }
+    }
+}
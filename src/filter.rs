@@ -0,0 +1,575 @@
+use crate::availability::{AvailabilityRow, Overbooking};
+use chrono::{Datelike, Weekday};
+use std::fmt;
+
+/// A boolean expression over row fields, e.g.
+/// `availability >= 2 && region == "Tuolumne Meadows" && dow in [Fri, Sat]`.
+///
+/// Fields are `date`, `dow`, `id`, `name`, `region`, `availability`, `quota` and `capacity`.
+/// Bare words that are not fields are strings, and string equality ignores ASCII case.
+/// `availability` is negative for overbooked rows unless they are clamped, like in the output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Expr);
+
+impl Filter {
+    pub fn parse(s: &str) -> Result<Filter, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {}", token));
+        }
+
+        match expr.kind()? {
+            Kind::Bool => Ok(Filter(expr)),
+            kind => Err(format!("filter must be true or false, not a {}", kind)),
+        }
+    }
+
+    /// Whether the row matches, reading `availability` under the overbooking policy.
+    pub fn matches(&self, row: &AvailabilityRow, overbooking: Overbooking) -> bool {
+        self.0.eval(row, overbooking) == Value::Bool(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Date,
+    Dow,
+    Id,
+    Name,
+    Region,
+    Availability,
+    Quota,
+    Capacity,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "date" => Some(Field::Date),
+            "dow" => Some(Field::Dow),
+            "id" => Some(Field::Id),
+            "name" => Some(Field::Name),
+            "region" => Some(Field::Region),
+            "availability" => Some(Field::Availability),
+            "quota" => Some(Field::Quota),
+            "capacity" => Some(Field::Capacity),
+            _ => None,
+        }
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            Field::Availability | Field::Quota | Field::Capacity => Kind::Num,
+            _ => Kind::Str,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::In => "in",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Field(Field),
+    Literal(Value),
+    List(Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Num,
+    Str,
+    List(ElementKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ElementKind {
+    Num,
+    Str,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Bool => write!(f, "boolean"),
+            Kind::Num => write!(f, "number"),
+            Kind::Str => write!(f, "string"),
+            Kind::List(_) => write!(f, "list"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Num(i64),
+    Str(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Str(a), Value::Str(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Expr {
+    /// Type checks the expression so evaluation can't fail on a row.
+    fn kind(&self) -> Result<Kind, String> {
+        match self {
+            Expr::Field(field) => Ok(field.kind()),
+            Expr::Literal(Value::Num(_)) => Ok(Kind::Num),
+            Expr::Literal(_) => Ok(Kind::Str),
+            Expr::List(items) => {
+                let mut element = None;
+                for item in items {
+                    let kind = match item.kind()? {
+                        Kind::Num => ElementKind::Num,
+                        Kind::Str => ElementKind::Str,
+                        kind => return Err(format!("lists can't contain a {}", kind)),
+                    };
+                    if element.is_some() && element != Some(kind) {
+                        return Err("lists must contain only numbers or only strings".to_string());
+                    }
+                    element = Some(kind);
+                }
+                Ok(Kind::List(element.unwrap_or(ElementKind::Str)))
+            }
+            Expr::Not(expr) => match expr.kind()? {
+                Kind::Bool => Ok(Kind::Bool),
+                kind => Err(format!("can't negate a {}", kind)),
+            },
+            Expr::And(a, b) | Expr::Or(a, b) => match (a.kind()?, b.kind()?) {
+                (Kind::Bool, Kind::Bool) => Ok(Kind::Bool),
+                (a, b) => Err(format!("can't combine a {} and a {}", a, b)),
+            },
+            Expr::Compare(op, a, b) => match (op, a.kind()?, b.kind()?) {
+                (Op::In, Kind::Num, Kind::List(ElementKind::Num))
+                | (Op::In, Kind::Str, Kind::List(ElementKind::Str)) => Ok(Kind::Bool),
+                (Op::In, a, b) => Err(format!("can't look for a {} in a {}", a, b)),
+                (_, Kind::Num, Kind::Num) | (_, Kind::Str, Kind::Str) => Ok(Kind::Bool),
+                (_, a, b) => Err(format!("can't compare a {} with a {}", a, b)),
+            },
+        }
+    }
+
+    fn eval(&self, row: &AvailabilityRow, overbooking: Overbooking) -> Value {
+        match self {
+            Expr::Field(field) => match field {
+                Field::Date => Value::Str(row.date.to_string()),
+                Field::Dow => Value::Str(row.date.weekday().to_string()),
+                Field::Id => Value::Str(row.trailhead.id.clone()),
+                Field::Name => Value::Str(row.trailhead.name.clone()),
+                Field::Region => {
+                    Value::Str(row.trailhead.region_name().unwrap_or_default().to_string())
+                }
+                Field::Availability => Value::Num(i64::from(row.availability(overbooking))),
+                Field::Quota => Value::Num(i64::from(row.trailhead.quota)),
+                Field::Capacity => Value::Num(i64::from(row.trailhead.capacity)),
+            },
+            Expr::Literal(value) => value.clone(),
+            Expr::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| item.eval(row, overbooking))
+                    .collect(),
+            ),
+            Expr::Not(expr) => Value::Bool(expr.eval(row, overbooking) != Value::Bool(true)),
+            Expr::And(a, b) => Value::Bool(
+                a.eval(row, overbooking) == Value::Bool(true)
+                    && b.eval(row, overbooking) == Value::Bool(true),
+            ),
+            Expr::Or(a, b) => Value::Bool(
+                a.eval(row, overbooking) == Value::Bool(true)
+                    || b.eval(row, overbooking) == Value::Bool(true),
+            ),
+            Expr::Compare(op, a, b) => {
+                let (a, b) = (a.eval(row, overbooking), b.eval(row, overbooking));
+                let result = match (op, &a, &b) {
+                    (Op::In, _, Value::List(items)) => items.iter().any(|item| a.equals(item)),
+                    (Op::Eq, _, _) => a.equals(&b),
+                    (Op::Ne, _, _) => !a.equals(&b),
+                    (op, Value::Num(a), Value::Num(b)) => ordered(*op, a, b),
+                    (op, Value::Str(a), Value::Str(b)) => ordered(*op, a, b),
+                    _ => false,
+                };
+                Value::Bool(result)
+            }
+        }
+    }
+}
+
+fn ordered<T: PartialOrd + ?Sized>(op: Op, a: &T, b: &T) -> bool {
+    match op {
+        Op::Lt => a < b,
+        Op::Le => a <= b,
+        Op::Gt => a > b,
+        Op::Ge => a >= b,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Num(i64),
+    Str(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    OpenList,
+    CloseList,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{:?}", word),
+            Token::Num(num) => write!(f, "{}", num),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Not => write!(f, "!"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::OpenList => write!(f, "["),
+            Token::CloseList => write!(f, "]"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        chars.next();
+        let next_is = |chars: &mut std::iter::Peekable<std::str::Chars>, expected| {
+            chars.next_if_eq(&expected).is_some()
+        };
+
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenList,
+            ']' => Token::CloseList,
+            ',' => Token::Comma,
+            '&' if next_is(&mut chars, '&') => Token::And,
+            '|' if next_is(&mut chars, '|') => Token::Or,
+            '=' if next_is(&mut chars, '=') => Token::Op(Op::Eq),
+            '!' if next_is(&mut chars, '=') => Token::Op(Op::Ne),
+            '!' => Token::Not,
+            '<' if next_is(&mut chars, '=') => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if next_is(&mut chars, '=') => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => string.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                Token::Str(string)
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.peek().is_some_and(char::is_ascii_digit)) =>
+            {
+                let mut digits = c.to_string();
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(c);
+                }
+                Token::Num(
+                    digits
+                        .parse()
+                        .map_err(|_| format!("{} is too large", digits))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "in" => Token::Op(Op::In),
+                    _ => Token::Word(word),
+                }
+            }
+            c => return Err(format!("unexpected character {:?}", c)),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "unexpected end of filter".to_string())?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), String> {
+        match self.next()? {
+            ref next if next == token => Ok(()),
+            next => Err(format!("expected {}, got {}", token, next)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.compare()
+        }
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let expr = self.primary()?;
+        match self.peek() {
+            Some(&Token::Op(op)) => {
+                self.position += 1;
+                let (mut a, mut b) = (expr, self.primary()?);
+                let dow = Expr::Field(Field::Dow);
+                if a == dow || b == dow {
+                    if !matches!(op, Op::Eq | Op::Ne | Op::In) {
+                        return Err(format!("can't compare days of the week with {}", op));
+                    }
+                    a = days(a)?;
+                    b = days(b)?;
+                }
+                Ok(Expr::Compare(op, Box::new(a), Box::new(b)))
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next()? {
+            Token::Open => {
+                let expr = self.or()?;
+                self.expect(&Token::Close)?;
+                Ok(expr)
+            }
+            Token::OpenList => {
+                let mut items = Vec::new();
+                if !self.eat(&Token::CloseList) {
+                    loop {
+                        items.push(self.primary()?);
+                        if self.eat(&Token::CloseList) {
+                            break;
+                        }
+                        self.expect(&Token::Comma)?;
+                    }
+                }
+                Ok(Expr::List(items))
+            }
+            Token::Num(num) => Ok(Expr::Literal(Value::Num(num))),
+            Token::Str(s) => Ok(Expr::Literal(Value::Str(s))),
+            Token::Word(word) => match Field::from_name(&word) {
+                Some(field) => Ok(Expr::Field(field)),
+                None => Ok(Expr::Literal(Value::Str(word))),
+            },
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+}
+
+/// Spells the days compared with `dow` the way rows do, e.g. friday as Fri, rejecting strings
+/// that are not days so they can't silently never match.
+fn days(expr: Expr) -> Result<Expr, String> {
+    match expr {
+        Expr::Literal(Value::Str(s)) => {
+            let day = s
+                .parse::<Weekday>()
+                .map_err(|_| format!("{:?} is not a day of the week", s))?;
+            Ok(Expr::Literal(Value::Str(day.to_string())))
+        }
+        Expr::List(items) => Ok(Expr::List(
+            items.into_iter().map(days).collect::<Result<_, _>>()?,
+        )),
+        expr => Ok(expr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, Overbooking};
+    use crate::filter::Filter;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn filter_rows() {
+        let cathedral = Trailhead {
+            quota: 15,
+            capacity: 25,
+            ..Trailhead::test("t21", "Cathedral Lakes", "tm")
+        };
+        // 2020-09-11 is a Friday
+        let friday = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2020, 9, 14).unwrap();
+
+        let filter = Filter::parse(
+            r#"availability >= 2 && region == "Tuolumne Meadows" && dow in [Fri, Sat]"#,
+        )
+        .unwrap();
+        assert!(filter.matches(
+            &AvailabilityRow::new(friday, &cathedral, 2),
+            Overbooking::Clamp
+        ));
+        assert!(!filter.matches(
+            &AvailabilityRow::new(friday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+        assert!(!filter.matches(
+            &AvailabilityRow::new(monday, &cathedral, 2),
+            Overbooking::Clamp
+        ));
+
+        let filter = Filter::parse(r#"!(name == "cathedral lakes") || date < "2020-09-12""#);
+        assert!(filter.as_ref().unwrap().matches(
+            &AvailabilityRow::new(friday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+        assert!(!filter.unwrap().matches(
+            &AvailabilityRow::new(monday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+
+        let filter = Filter::parse("dow == friday").unwrap();
+        assert!(filter.matches(
+            &AvailabilityRow::new(friday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+        let filter = Filter::parse("friday == dow").unwrap();
+        assert!(filter.matches(
+            &AvailabilityRow::new(friday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+
+        let overbooked = AvailabilityRow::from_occupancy(friday, &cathedral, 18, 15);
+        let filter = Filter::parse("availability < 0 && availability >= -3").unwrap();
+        assert!(filter.matches(&overbooked, Overbooking::Negative));
+        assert!(!filter.matches(&overbooked, Overbooking::Clamp));
+        let filter = Filter::parse("availability == 0").unwrap();
+        assert!(filter.matches(&overbooked, Overbooking::Clamp));
+        assert!(!filter.matches(
+            &AvailabilityRow::new(friday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+
+        let filter = Filter::parse("id in [t21, t24b] && quota < capacity").unwrap();
+        assert!(filter.matches(
+            &AvailabilityRow::new(monday, &cathedral, 1),
+            Overbooking::Clamp
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_filters() {
+        assert!(Filter::parse("availability").is_err());
+        assert!(Filter::parse("availability >= \"2\"").is_err());
+        assert!(Filter::parse("dow in [Fri, 2]").is_err());
+        assert!(Filter::parse("availability > 2 &&").is_err());
+        assert!(Filter::parse("(availability > 2").is_err());
+        assert!(Filter::parse("name == \"Lyell").is_err());
+        assert!(Filter::parse("availability > 2 name").is_err());
+        assert_eq!(
+            Filter::parse("dow in [Fri, Fir]"),
+            Err("\"Fir\" is not a day of the week".to_string())
+        );
+        assert_eq!(
+            Filter::parse("dow < Fri"),
+            Err("can't compare days of the week with <".to_string())
+        );
+        assert_eq!(
+            Filter::parse("availability >= >= 2"),
+            Err("unexpected >=".to_string())
+        );
+    }
+}
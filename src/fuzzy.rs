@@ -0,0 +1,40 @@
+/// Loose, case-insensitive name matching. Every word of the query has to appear in order
+/// within some word of the text, so `lyell`, `glacier pt` and `tuol` all find their trailhead
+/// or region.
+pub fn matches(query: &str, text: &str) -> bool {
+    let words = words(text);
+    let mut query = words_of(query).peekable();
+    query.peek().is_some() && query.all(|part| words.iter().any(|word| subsequence(&part, word)))
+}
+
+fn words(text: &str) -> Vec<String> {
+    words_of(text).collect()
+}
+
+fn words_of(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+fn subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fuzzy::matches;
+
+    #[test]
+    fn fuzzy_names() {
+        assert!(matches("lyell", "Lyell Canyon"));
+        assert!(matches("LYELL", "Lyell Canyon"));
+        assert!(matches("white wolf", "Tioga Road, White Wolf"));
+        assert!(matches("glacier pt", "Glacier Point"));
+        assert!(matches("tuol", "Tuolumne Meadows"));
+        assert!(!matches("lyell", "Cathedral Lakes"));
+        assert!(!matches("pt glacier x", "Glacier Point"));
+        assert!(!matches("", "Glacier Point"));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::availability::AvailabilityRow;
+use crate::store::Snapshot;
+use crate::Selection;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Args;
+use std::collections::BTreeMap;
+
+/// Show how open spots for each trailhead and date changed across the stored snapshots
+#[derive(Debug, Args)]
+pub struct History {
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+/// One trailhead on one date, as seen by each snapshot that fetched it, oldest first.
+pub type Series<'a> = Vec<(DateTime<Utc>, AvailabilityRow<'a>)>;
+
+/// A series per trailhead and date, ordered like the rows. Series are kept whole when any of
+/// their points is selected, so spots running out still show.
+pub fn series<'a>(
+    snapshots: &'a [Snapshot],
+    selection: &Selection,
+    today: NaiveDate,
+) -> Vec<Series<'a>> {
+    let mut series = BTreeMap::<_, Series>::new();
+    for snapshot in snapshots {
+        for row in snapshot.rows() {
+            series
+                .entry(row.key())
+                .or_default()
+                .push((snapshot.fetched, row));
+        }
+    }
+
+    series
+        .into_values()
+        .filter(|points| points.iter().any(|(_, row)| selection.matches(row, today)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::series;
+    use crate::store::Store;
+    use crate::{ReportDate, Selection, Trailhead};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rusqlite::Connection;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn series_per_trailhead_and_date() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let trailheads = vec![(lyell.id.clone(), lyell)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let date = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let report = |occupancy| ReportDate {
+            date,
+            values: vec![("t24b".to_string(), occupancy)].into_iter().collect(),
+        };
+
+        let mut store = Store::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for (day, occupancy) in [(1, 2), (2, 5), (3, 6)] {
+            let fetched = Utc.with_ymd_and_hms(2020, 9, day, 12, 0, 0).unwrap();
+            store
+                .record(fetched, "upstream", &trailheads, &[report(occupancy)])
+                .unwrap();
+        }
+        let snapshots = store.snapshots().unwrap();
+
+        let selection = Selection {
+            trailhead: vec!["lyell".to_string()],
+            min_availability: Some(3),
+            ..Selection::default()
+        };
+        let series = series(&snapshots, &selection, date);
+        assert_eq!(series.len(), 1);
+        let points = series[0]
+            .iter()
+            .map(|(fetched, row)| (fetched.date_naive(), row.availability))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            vec![
+                (NaiveDate::from_ymd_opt(2020, 9, 1).unwrap(), 4),
+                (NaiveDate::from_ymd_opt(2020, 9, 2).unwrap(), 1),
+                (NaiveDate::from_ymd_opt(2020, 9, 3).unwrap(), 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::history::Series;
+use crate::Selection;
+use crate::Trailhead;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Rank trailheads by how quickly they fill across the stored snapshots, then by how full they
+/// are now
+#[derive(Debug, Args)]
+pub struct Hot {
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+/// How a trailhead fills, over all of its dates.
+#[derive(Debug, Clone, Copy)]
+pub struct Heat<'a> {
+    pub trailhead: &'a Trailhead,
+    /// Average over the dates in the latest snapshot
+    pub percent_full: Option<u32>,
+    /// Dates seen going from open to full
+    pub filled: usize,
+    /// Median time from first seen open to first seen full
+    pub minutes_to_fill: Option<i64>,
+}
+
+/// Time from the first point with open spots to the first full point after it.
+fn minutes_to_fill(series: &Series) -> Option<i64> {
+    let open = series.iter().position(|(_, row)| row.availability > 0)?;
+    let (opened, _) = series[open];
+    let (filled, _) = series[open..]
+        .iter()
+        .find(|(_, row)| row.availability == 0)?;
+    Some((*filled - opened).num_minutes())
+}
+
+/// Trailheads that fill fastest first. Trailheads never seen filling come last, the fullest
+/// of them first.
+pub fn rank<'a>(series: &[Series<'a>], latest: Option<DateTime<Utc>>) -> Vec<Heat<'a>> {
+    let mut trailheads = BTreeMap::<_, (&Trailhead, Vec<i64>, Vec<u32>)>::new();
+    for points in series {
+        let (fetched, last) = match points.last() {
+            Some(point) => *point,
+            None => continue,
+        };
+
+        let (_, fills, percents) = trailheads
+            .entry(last.trailhead.sort_key())
+            .or_insert_with(|| (last.trailhead, Vec::new(), Vec::new()));
+        fills.extend(minutes_to_fill(points));
+        if Some(fetched) == latest {
+            percents.extend(last.percent_full());
+        }
+    }
+
+    let mut heat = trailheads
+        .into_values()
+        .map(|(trailhead, mut fills, percents)| {
+            fills.sort_unstable();
+            let count = percents.len() as u32;
+            Heat {
+                trailhead,
+                percent_full: percents.iter().sum::<u32>().checked_div(count),
+                filled: fills.len(),
+                minutes_to_fill: fills.get(fills.len() / 2).copied(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    heat.sort_by_key(|heat| {
+        (
+            heat.minutes_to_fill.is_none(),
+            heat.minutes_to_fill,
+            Reverse(heat.percent_full),
+        )
+    });
+    heat
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::hot::rank;
+    use crate::Trailhead;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn fastest_to_fill_first() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let glen_aulin = Trailhead::test("t22", "Glen Aulin", "tm");
+        let date = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2020, 9, 1, hour, 0, 0).unwrap();
+        let series = |trailhead, spots: &[u8]| {
+            spots
+                .iter()
+                .enumerate()
+                .map(|(hour, &spots)| {
+                    (
+                        at(hour as u32),
+                        AvailabilityRow::new(date, trailhead, spots),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let series = vec![
+            series(&lyell, &[5, 2, 0]),
+            series(&cathedral, &[0, 4, 0]),
+            series(&glen_aulin, &[9, 8, 7]),
+        ];
+        let heat = rank(&series, Some(at(2)));
+
+        let ranked = heat
+            .iter()
+            .map(|heat| {
+                (
+                    heat.trailhead.id.as_str(),
+                    heat.minutes_to_fill,
+                    heat.percent_full,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranked,
+            vec![
+                ("t21", Some(60), Some(100)),
+                ("t24b", Some(120), Some(100)),
+                ("t22", None, Some(30))
+            ]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::BTreeMap;
+
+/// An iCalendar document with an all day event per date, listing each open count in the
+/// description. Event ids only depend on the date so importing a newer file updates the
+/// existing events.
+pub fn calendar(days: &BTreeMap<NaiveDate, Vec<(String, u32)>>, stamp: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//yosemite-wilderness-permits//yose//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for (date, counts) in days {
+        let total = counts.iter().map(|(_, count)| count).sum::<u32>();
+        let description = counts
+            .iter()
+            .map(|(label, count)| format!("{}: {}", label, count))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        lines.extend(vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@yosemite-wilderness-permits", date.format("%Y%m%d")),
+            format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                (*date + Duration::days(1)).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape(&format!("{} permits open", total))),
+            format!("DESCRIPTION:{}", escape(&description)),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Lines are limited to 75 bytes, longer ones continue on the next line after a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ics::calendar;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn event_per_date() {
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let stamp = Utc.with_ymd_and_hms(2020, 9, 1, 7, 0, 0).unwrap();
+        let mut days = BTreeMap::new();
+        days.insert(
+            date,
+            vec![
+                ("Lyell Canyon".to_string(), 4),
+                ("Tioga Road, White Wolf".to_string(), 2),
+            ],
+        );
+
+        let ics = calendar(&days, stamp);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20200910\r\n"));
+        assert!(ics.contains("\r\nDTEND;VALUE=DATE:20200911\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:6 permits open\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:Lyell Canyon: 4\\nTioga Road\\, White Wolf: 2\r\n"));
+
+        let long = "x".repeat(200);
+        days.insert(date, vec![(long, 1)]);
+        let ics = calendar(&days, stamp);
+        assert!(ics
+            .lines()
+            .all(|line| line.trim_end_matches('\r').len() <= 75));
+    }
+}
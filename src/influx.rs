@@ -0,0 +1,74 @@
+use crate::availability::AvailabilityRow;
+use chrono::{DateTime, Utc};
+use http::header::AUTHORIZATION;
+use std::error::Error;
+
+/// A point per trailhead and date in InfluxDB line protocol, tagged with the date, region and
+/// trailhead name, with the open spots (negative when overbooked) as the `remaining` field.
+/// Every point is stamped with the time the availability was fetched, in nanoseconds.
+pub fn lines(rows: &[AvailabilityRow], fetched: DateTime<Utc>) -> String {
+    let timestamp = fetched.timestamp_nanos_opt().unwrap_or_default();
+
+    rows.iter()
+        .map(|row| {
+            let mut line = format!("availability,date={}", row.date);
+            if let Some(region) = row.trailhead.region_name() {
+                line.push_str(&format!(",region={}", escape(region)));
+            }
+            line.push_str(&format!(
+                ",trailhead={} remaining={}i {}\n",
+                escape(&row.trailhead.name),
+                row.remaining(),
+                timestamp
+            ));
+            line
+        })
+        .collect()
+}
+
+/// Writes the lines to an InfluxDB write endpoint, e.g.
+/// `http://localhost:8086/api/v2/write?org=me&bucket=yose`.
+pub async fn push(url: &str, token: Option<&str>, lines: String) -> Result<(), Box<dyn Error>> {
+    let mut request = reqwest::Client::new().post(url).body(lines);
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Token {}", token));
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Tag values can't have unescaped commas, equals signs or spaces.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::influx::lines;
+    use crate::Trailhead;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn line_protocol() {
+        let white_wolf = Trailhead::test("t34", "Tioga Road, White Wolf", "bf");
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let rows = vec![
+            AvailabilityRow::new(date, &white_wolf, 4),
+            AvailabilityRow::from_occupancy(date, &white_wolf, 12, 10),
+        ];
+        let fetched = Utc.with_ymd_and_hms(2020, 9, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            lines(&rows, fetched),
+            "availability,date=2020-09-10,region=Big\\ Oak\\ Flat\\ &\\ Tioga\\ Road,\
+             trailhead=Tioga\\ Road\\,\\ White\\ Wolf remaining=4i 1598961600000000000\n\
+             availability,date=2020-09-10,region=Big\\ Oak\\ Flat\\ &\\ Tioga\\ Road,\
+             trailhead=Tioga\\ Road\\,\\ White\\ Wolf remaining=-2i 1598961600000000000\n"
+        );
+    }
+}
@@ -1,35 +1,483 @@
-use chrono::{NaiveDate, NaiveDateTime, Utc};
+mod availability;
+mod clipboard;
+mod columns;
+mod compare;
+mod diff;
+mod export;
+mod fees;
+mod filter;
+mod fuzzy;
+mod history;
+mod hot;
+mod ics;
+mod influx;
+mod markup;
+mod matrix;
+mod mirror;
+mod output;
+mod regions;
+mod repl;
+mod store;
+mod summary;
+mod table;
+mod timing;
+mod trips;
+
+use crate::availability::{AvailabilityRow, AvailabilitySet, Overbooking};
+use crate::columns::{Column, ColumnSpec};
+use crate::compare::Compare;
+use crate::diff::{Baseline, Change, Diff};
+use crate::export::{Export, ExportFormat};
+use crate::fees::{Cost, PermitSource};
+use crate::filter::Filter;
+use crate::history::History;
+use crate::hot::Hot;
+use crate::markup::ReportFormat;
+use crate::mirror::{Mirror, Source};
+use crate::output::{Format, JsonAvailability, SCHEMA_VERSION};
+use crate::store::{Snapshot, Store};
+use crate::summary::Summary;
+use crate::timing::Timings;
+use crate::trips::{Exits, Trips};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, SecondsFormat, Utc, Weekday};
 use chrono_tz::US::Pacific;
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use dialoguer::Input;
+use futures::stream::{FuturesOrdered, StreamExt};
 use http::header::{
     HeaderName, ACCEPT, ACCEPT_LANGUAGE, CACHE_CONTROL, CONTENT_TYPE, COOKIE, PRAGMA, REFERER,
     USER_AGENT,
 };
 use http::{HeaderMap, HeaderValue};
-use serde::export::Formatter;
 use serde::{Deserialize, Serialize};
-use std::cmp::min;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
 use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::future::Future;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+#[derive(Debug, Parser)]
+#[command(
+    about = "Find open Yosemite wilderness permit trailheads",
+    after_help = "Every option can also be set through the YOSE_* environment variable shown next to it.",
+    args_conflicts_with_subcommands = true
+)]
+struct Opts {
+    /// Session cookie for yosemite.org, prompted for when unset (the legacy COOKIE variable is
+    /// also read)
+    #[arg(long, env = "YOSE_COOKIE", hide_env_values = true, global = true)]
+    cookie: Option<String>,
+
+    /// Where to read availability from, upstream, mirror=<url> for the document another
+    /// instance published with --format json, or store=<path> for the latest snapshot recorded
+    /// with --store. Mirrors and stores need no cookie
+    #[arg(
+        long,
+        env = "YOSE_SOURCE",
+        value_parser = Source::parse,
+        default_value = "upstream",
+        global = true
+    )]
+    source: Source,
+
+    /// Record the fetched trailheads and reports in this SQLite database, created when missing
+    #[arg(long, env = "YOSE_STORE", global = true)]
+    store: Option<PathBuf>,
+
+    /// Print how long each step took to stderr when done
+    #[arg(long, env = "YOSE_TIMING", global = true)]
+    timing: bool,
+
+    // flattened so the availability options still work without naming the command
+    #[command(flatten)]
+    availability: Availability,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    Availability(Availability),
+    Trailheads(TrailheadList),
+    Report(RawReport),
+    Compare(Compare),
+    Export(Export),
+    Trips(Trips),
+    Diff(Diff),
+    CompareBaseline(Baseline),
+    History(History),
+    Hot(Hot),
+}
+
+impl Command {
+    /// Whether the command uses the trailhead at all.
+    fn includes(&self, trailhead: &Trailhead) -> bool {
+        match self {
+            Command::Availability(availability) => availability.selection.includes(trailhead),
+            Command::Trailheads(list) => list.includes(trailhead),
+            Command::Report(report) => report.selection.includes(trailhead),
+            Command::Compare(compare) => compare.selection.includes(trailhead),
+            Command::Export(export) => export.selection.includes(trailhead),
+            Command::Trips(trips) => trips.includes(trailhead),
+            Command::Diff(diff) => diff.selection.includes(trailhead),
+            Command::CompareBaseline(baseline) => baseline.selection.includes(trailhead),
+            Command::History(history) => history.selection.includes(trailhead),
+            Command::Hot(hot) => hot.selection.includes(trailhead),
+        }
+    }
+}
+
+/// Which rows to include and how to name their trailheads.
+#[derive(Debug, Args)]
+#[cfg_attr(test, derive(Default))]
+struct Selection {
+    /// Only show rows matching an expression, e.g.
+    /// 'availability >= 2 && region == "Tuolumne Meadows" && dow in [Fri, Sat]'
+    #[arg(long, env = "YOSE_FILTER", value_parser = Filter::parse)]
+    filter: Option<Filter>,
+
+    /// Only include trailheads matching this name, loosely and ignoring case, e.g. lyell.
+    /// Repeat for more trailheads
+    #[arg(long, env = "YOSE_TRAILHEAD")]
+    trailhead: Vec<String>,
+
+    /// Only include and fetch these regions, by code or loosely by name, e.g. tm,valley
+    #[arg(long, env = "YOSE_REGION", value_delimiter = ',')]
+    region: Vec<String>,
+
+    /// Only include dates with at least this many open spots, e.g. the size of your party
+    #[arg(long, env = "YOSE_MIN_AVAILABILITY", visible_alias = "party-size")]
+    min_availability: Option<u8>,
+
+    /// Prefix trailhead names with their region, e.g. "Tuolumne Meadows / Cathedral Lakes"
+    #[arg(long, env = "YOSE_REGION_NAMES")]
+    region_names: bool,
+
+    /// Keep trailheads with nothing open, to see which ones are booked out
+    #[arg(long, env = "YOSE_INCLUDE_FULL")]
+    include_full: bool,
+
+    /// How to show trailheads booked past their limit
+    #[arg(long, env = "YOSE_OVERBOOKED", value_enum, default_value = "clamp")]
+    overbooked: Overbooking,
+
+    /// Only include dates on or after this one, e.g. jun-10 or 2021-06-10
+    #[arg(long, env = "YOSE_FROM", value_parser = parse_date)]
+    from: Option<NaiveDate>,
+
+    /// Only include dates on or before this one, in the same format
+    #[arg(long, env = "YOSE_TO", value_parser = parse_date)]
+    to: Option<NaiveDate>,
+
+    /// Only include the next N days, starting today
+    #[arg(long, env = "YOSE_NEXT_DAYS", conflicts_with_all = ["from", "to"])]
+    next_days: Option<u32>,
+
+    /// Only include these days of the week, e.g. fri,sat
+    #[arg(long, env = "YOSE_DOW", value_delimiter = ',', value_parser = parse_weekday)]
+    dow: Vec<Weekday>,
+
+    /// Only include Saturdays and Sundays
+    #[arg(long, env = "YOSE_WEEKENDS", conflicts_with_all = ["dow", "weekdays"])]
+    weekends: bool,
+
+    /// Only include Mondays through Fridays
+    #[arg(long, env = "YOSE_WEEKDAYS", conflicts_with = "dow")]
+    weekdays: bool,
+}
+
+impl Selection {
+    /// Whether the trailhead matches the trailhead and region names. The Donohue Pass exits
+    /// are quotas rather than places to start from, and are never included.
+    fn includes(&self, trailhead: &Trailhead) -> bool {
+        let names = &self.trailhead;
+        !trips::is_donohue_exit(trailhead)
+            && (names.is_empty()
+                || names
+                    .iter()
+                    .any(|name| fuzzy::matches(name, &trailhead.name)))
+            && (self.region.is_empty() || self.region.iter().any(|r| trailhead.in_region(r)))
+    }
+
+    /// Whether the row's trailhead is included, it falls inside the date range and it matches
+    /// the filter.
+    fn matches(&self, row: &AvailabilityRow, today: NaiveDate) -> bool {
+        let (from, to) = match self.next_days {
+            Some(days) => (
+                Some(today),
+                Some(today + chrono::Duration::days(i64::from(days) - 1)),
+            ),
+            None => (self.from, self.to),
+        };
+
+        self.includes(row.trailhead)
+            && self
+                .min_availability
+                .is_none_or(|min| row.availability(self.overbooked) >= i16::from(min))
+            && from.is_none_or(|from| from <= row.date)
+            && to.is_none_or(|to| row.date <= to)
+            && self.on_day(row.date.weekday())
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(row, self.overbooked))
+    }
+
+    /// Whether the day of the week is in --dow, and is on a weekend or weekday if asked for.
+    fn on_day(&self, day: Weekday) -> bool {
+        let weekend = matches!(day, Weekday::Sat | Weekday::Sun);
+        (self.dow.is_empty() || self.dow.contains(&day))
+            && (!self.weekends || weekend)
+            && (!self.weekdays || !weekend)
+    }
+}
+
+fn parse_sort_column(s: &str) -> Result<Column, String> {
+    if s.eq_ignore_ascii_case("trailhead") {
+        Ok(Column::Name)
+    } else {
+        Column::parse(s)
+    }
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday, String> {
+    s.parse()
+        .map_err(|_| format!("expected a day of the week like fri, got {:?}", s))
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    compare::parse_date(s, today())
+}
+
+/// List open trailheads per date (the default when no command is given)
+#[derive(Debug, Args)]
+struct Availability {
+    #[command(flatten)]
+    selection: Selection,
+
+    /// Print these columns, optionally renamed, e.g. date,name=trailhead,availability
+    /// (date, id, name, region, availability, occupancy, quota, capacity, cost, status,
+    /// percent_full, limit)
+    #[arg(
+        long,
+        env = "YOSE_COLUMNS",
+        value_delimiter = ',',
+        value_parser = ColumnSpec::parse
+    )]
+    columns: Option<Vec<ColumnSpec>>,
+
+    /// Output format; tables only show availability, and JSON rows are objects keyed by column
+    /// header when --columns is given. JSON documents and records carry a schema_version, the
+    /// other formats don't
+    #[arg(long, env = "YOSE_FORMAT", value_enum)]
+    format: Option<Format>,
+
+    /// Add an estimated permit cost column for a group of this size
+    #[arg(long, env = "YOSE_GROUP_SIZE")]
+    group_size: Option<u8>,
+
+    /// Include the Half Dome add-on in cost estimates for trailheads that allow it
+    #[arg(long, env = "YOSE_HALF_DOME", requires = "group_size")]
+    half_dome: bool,
+
+    /// Order the output by region first, then by date
+    #[arg(long, env = "YOSE_GROUP_BY_REGION")]
+    group_by_region: bool,
+
+    /// Order rows by this column instead of by date and name, e.g. availability (trailhead is
+    /// the same as name)
+    #[arg(long, env = "YOSE_SORT_BY", value_parser = parse_sort_column)]
+    sort_by: Option<Column>,
+
+    /// Sort largest first
+    #[arg(long, env = "YOSE_DESC")]
+    desc: bool,
+
+    /// Write the output to this file instead of stdout
+    #[arg(long, short, env = "YOSE_OUTPUT")]
+    output: Option<PathBuf>,
+
+    /// Also copy the output to the system clipboard
+    #[arg(long, env = "YOSE_COPY")]
+    copy: bool,
+
+    /// After fetching, read commands from stdin to filter and sort the rows without refetching
+    #[arg(long, env = "YOSE_INTERACTIVE", conflicts_with_all = ["copy", "output"])]
+    interactive: bool,
+
+    /// Show how full each trailhead is as a percentage of the quota or capacity that applies on
+    /// the date, next to the occupancy and that limit, instead of the open spots
+    #[arg(long, env = "YOSE_PERCENT_FULL", conflicts_with = "summary")]
+    percent_full: bool,
+
+    /// Print open spots rolled up per region instead of one row per trailhead, both added up
+    /// and the most at any one trailhead
+    #[arg(long, env = "YOSE_SUMMARY", value_enum, visible_alias = "summarize")]
+    summary: Option<Summary>,
+
+    /// Send --format influx lines to this InfluxDB write endpoint instead of printing them,
+    /// e.g. http://localhost:8086/api/v2/write?org=me&bucket=yose
+    #[arg(long, env = "YOSE_INFLUX_URL")]
+    influx_url: Option<String>,
+
+    /// API token for --influx-url
+    #[arg(
+        long,
+        env = "YOSE_INFLUX_TOKEN",
+        hide_env_values = true,
+        requires = "influx_url"
+    )]
+    influx_token: Option<String>,
+}
+
+impl Availability {
+    /// The selected columns, or date, name and availability, followed by occupancy when flagging
+    /// overbooked trailheads, status when including full ones and cost when a group size is
+    /// given. Availability is replaced by percent full, occupancy and limit with --percent-full.
+    fn columns(&self) -> Vec<ColumnSpec> {
+        self.columns_or(&[Column::Date, Column::Name, Column::Availability])
+    }
+
+    /// The selected columns, or `defaults` with the same occupancy, status and cost additions.
+    fn columns_or(&self, defaults: &[Column]) -> Vec<ColumnSpec> {
+        let columns = self.columns.clone().unwrap_or_else(|| {
+            let mut columns = defaults.to_vec();
+            if self.percent_full {
+                columns = columns
+                    .into_iter()
+                    .flat_map(|column| match column {
+                        Column::Availability => {
+                            vec![Column::PercentFull, Column::Occupancy, Column::Limit]
+                        }
+                        column => vec![column],
+                    })
+                    .collect();
+            }
+            if self.selection.overbooked == Overbooking::Flag && !self.percent_full {
+                columns.push(Column::Occupancy);
+            }
+            if self.selection.include_full {
+                columns.push(Column::Status);
+            }
+            if self.group_size.is_some() {
+                columns.push(Column::Cost);
+            }
+            columns.into_iter().map(ColumnSpec::from).collect()
+        });
+
+        if self.group_size.is_none() && columns.iter().any(|spec| spec.column == Column::Cost) {
+            Opts::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "the cost column requires --group-size",
+                )
+                .exit();
+        }
+
+        columns
+    }
+
+    /// JSON lines carry the id and region by default so each line stands on its own.
+    fn jsonl_columns(&self) -> Vec<ColumnSpec> {
+        self.columns_or(&[
+            Column::Date,
+            Column::Id,
+            Column::Name,
+            Column::Region,
+            Column::Availability,
+        ])
+    }
+
+    fn cost(&self, row: &AvailabilityRow, now: NaiveDate) -> Option<Cost> {
+        self.group_size.map(|group_size| {
+            let fees = PermitSource::for_date(row.date, now).fees();
+            fees.estimate(row.trailhead, group_size, self.half_dome)
+        })
+    }
+
+    /// The chosen format, or a table when printing plain availability to a terminal.
+    fn format(&self) -> Format {
+        self.format.unwrap_or_else(|| {
+            let plain = self.columns.is_none() && self.group_size.is_none() && !self.percent_full;
+            if plain && self.output.is_none() && io::stdout().is_terminal() {
+                Format::Table
+            } else {
+                Format::Csv
+            }
+        })
+    }
+
+    /// Whether JSON lines can be written as each region arrives. Summaries, sorting, the prompt
+    /// and copying need every row first.
+    fn streamed(&self) -> bool {
+        let sorted = self.sort_by.is_some() || self.desc || self.group_by_region;
+        self.format() == Format::Jsonl
+            && self.summary.is_none()
+            && !sorted
+            && !self.interactive
+            && !self.copy
+    }
+
+    /// Whether tables are colored, only when they are printed straight to a terminal.
+    fn styled(&self) -> bool {
+        self.output.is_none() && !self.copy && io::stdout().is_terminal()
+    }
+}
+
+/// List every trailhead with its region, quota and capacity
+#[derive(Debug, Args)]
+struct TrailheadList {
+    /// Only list trailheads in these regions, by code or loosely by name, e.g. tm,valley
+    #[arg(long, env = "YOSE_REGION", value_delimiter = ',')]
+    region: Vec<String>,
+}
+
+impl TrailheadList {
+    fn includes(&self, trailhead: &Trailhead) -> bool {
+        self.region.is_empty() || self.region.iter().any(|r| trailhead.in_region(r))
+    }
+}
+
+/// Print the raw occupancy reported for each trailhead id and date, or an availability report
+/// to share
+#[derive(Debug, Args)]
+struct RawReport {
+    #[arg(long, env = "YOSE_REPORT_FORMAT", value_enum, default_value = "csv")]
+    format: ReportFormat,
+
+    /// Only the regions apply to the raw occupancy, everything applies to the html and md
+    /// reports
+    #[command(flatten)]
+    selection: Selection,
+}
+
+const QUERY_URL: &str = "https://yosemite.org/wp-content/plugins/wildtrails/query.php";
 
 struct YoseClient {
+    url: String,
     common_headers: HeaderMap,
     client: reqwest::Client,
 }
 
 impl YoseClient {
-    fn new(cookies: &str) -> YoseClient {
-        YoseClient {
-            common_headers: common_headers(cookies),
+    fn new(cookies: &str) -> Result<YoseClient, Box<dyn Error>> {
+        Ok(YoseClient {
+            url: QUERY_URL.to_string(),
+            common_headers: common_headers(cookies)?,
+            // gzip and brotli responses are requested and decoded by reqwest
             client: reqwest::Client::new(),
-        }
+        })
     }
 
-    fn get(self: &Self) -> reqwest::RequestBuilder {
+    fn get(&self) -> reqwest::RequestBuilder {
         self.client
-            .get("https://yosemite.org/wp-content/plugins/wildtrails/query.php")
+            .get(self.url.as_str())
             .headers(self.common_headers.clone())
     }
 
@@ -66,13 +514,55 @@ impl YoseClient {
             .response
             .values
             .into_iter()
-            .filter_map(|dict| convert_report_values(dict))
+            .filter_map(convert_report_values)
             .collect();
 
         Ok(parsed)
     }
 }
 
+/// The upstream site or a mirror of it, answering the same queries.
+enum Backend {
+    Upstream(YoseClient),
+    Mirror(Mirror),
+    Store(Snapshot),
+}
+
+impl Backend {
+    /// Where the availability was originally fetched from.
+    fn url(&self) -> &str {
+        match self {
+            Backend::Upstream(client) => &client.url,
+            Backend::Mirror(mirror) => &mirror.url,
+            Backend::Store(snapshot) => &snapshot.source,
+        }
+    }
+
+    /// When stored snapshots were fetched, `None` for anything fetched now.
+    fn fetched(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Backend::Store(snapshot) => Some(snapshot.fetched),
+            _ => None,
+        }
+    }
+
+    async fn fetch_trailheads(&self) -> Result<BTreeMap<String, Trailhead>, Box<dyn Error>> {
+        match self {
+            Backend::Upstream(client) => Ok(client.fetch_trailheads().await?.values),
+            Backend::Mirror(mirror) => Ok(mirror.trailheads.clone()),
+            Backend::Store(snapshot) => Ok(snapshot.trailheads.clone()),
+        }
+    }
+
+    async fn fetch_report(&self, region: &str) -> Result<Vec<ReportDate>, Box<dyn Error>> {
+        match self {
+            Backend::Upstream(client) => client.fetch_report(region).await,
+            Backend::Mirror(mirror) => Ok(mirror.report(region)),
+            Backend::Store(snapshot) => Ok(snapshot.report(region)),
+        }
+    }
+}
+
 fn convert_report_values(mut dict: BTreeMap<String, ReportValue>) -> Option<ReportDate> {
     let date = match dict.remove("date")? {
         ReportValue::Date(date) => date,
@@ -93,17 +583,27 @@ fn convert_report_values(mut dict: BTreeMap<String, ReportValue>) -> Option<Repo
 #[derive(Debug)]
 enum YosemiteError {
     UnexpectedResponse(Status),
+    InvalidCookie,
 }
 
 impl fmt::Display for YosemiteError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YosemiteError::UnexpectedResponse(status) => write!(
+                f,
+                "unexpected response: {} ({})",
+                status.r#type, status.value
+            ),
+            YosemiteError::InvalidCookie => {
+                write!(f, "cookie is empty or not a valid header value")
+            }
+        }
     }
 }
 
 impl Error for YosemiteError {}
 
-fn common_headers(cookies: &str) -> HeaderMap {
+fn common_headers(cookies: &str) -> Result<HeaderMap, YosemiteError> {
     let mut header_map =
         vec![
             (ACCEPT, "*/*"),
@@ -123,84 +623,730 @@ fn common_headers(cookies: &str) -> HeaderMap {
         .map(|(k, v)| (k, HeaderValue::from_static(v)))
         .collect::<HeaderMap>();
 
-    header_map.insert(COOKIE, HeaderValue::from_str(cookies).unwrap());
+    let cookies = cookies.trim();
+    if cookies.is_empty() {
+        return Err(YosemiteError::InvalidCookie);
+    }
+
+    let cookie = HeaderValue::from_str(cookies).map_err(|_| YosemiteError::InvalidCookie)?;
+    header_map.insert(COOKIE, cookie);
 
-    header_map
+    Ok(header_map)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let cookies =
-        env::var("COOKIE").or_else(|_| Input::new().with_prompt("Cookie plz").interact())?;
+    let opts = Opts::parse();
+    let timing = opts.timing;
 
-    let client = YoseClient::new(cookies.as_str());
+    let mut timings = Timings::default();
+    let result = run(opts, &mut timings).await;
+    if timing {
+        eprint!("{}", timings.report());
+    }
+
+    result
+}
+
+async fn run(opts: Opts, timings: &mut Timings) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let command = opts
+        .command
+        .unwrap_or(Command::Availability(opts.availability));
+
+    if let Command::Availability(availability) = &command {
+        if availability.influx_url.is_some() && availability.format() != Format::Influx {
+            return Err("--influx-url needs --format influx".into());
+        }
+    }
 
-    let trailheads = client.fetch_trailheads().await?.values;
+    // these only read the store, there is nothing to fetch
+    if let Command::Diff(_) | Command::History(_) | Command::Hot(_) = &command {
+        let path = opts
+            .store
+            .as_ref()
+            .ok_or("diff, history and hot read snapshots from --store")?;
+        let store = Store::open(path)?;
+        let output = match &command {
+            Command::Diff(diff) => render_diff(diff, &store)?,
+            Command::History(history) => render_history(history, &store)?,
+            Command::Hot(hot) => render_hot(hot, &store)?,
+            _ => unreachable!("only commands reading the store"),
+        };
+        let start = timings.record("store", start);
+        print!("{}", output);
+        timings.record("output", start);
 
+        return Ok(());
+    }
+
+    let client = match opts.source {
+        Source::Upstream => {
+            let cookies = match opts.cookie {
+                Some(cookies) => cookies,
+                None => env::var("COOKIE")
+                    .or_else(|_| Input::new().with_prompt("Cookie plz").interact())?,
+            };
+
+            Backend::Upstream(YoseClient::new(cookies.as_str())?)
+        }
+        Source::Mirror(url) => Backend::Mirror(Mirror::fetch(&url, today()).await?),
+        Source::Store(path) => match Store::open(&path)?.latest()? {
+            Some(snapshot) => Backend::Store(snapshot),
+            None => return Err(format!("no snapshots in {}", path.display()).into()),
+        },
+    };
+    let start = timings.record("session", start);
+
+    let trailheads = client.fetch_trailheads().await?;
+    let start = timings.record("trailheads", start);
+
+    if let Command::Trailheads(list) = &command {
+        let mut writer = csv::Writer::from_writer(io::stdout());
+        writer.write_record(["id", "region", "name", "quota", "capacity"])?;
+        for trailhead in sorted(&trailheads).filter(|trailhead| list.includes(trailhead)) {
+            writer.write_record([
+                trailhead.id.as_str(),
+                trailhead.region.as_deref().unwrap_or_default(),
+                trailhead.name.as_str(),
+                &trailhead.quota.to_string(),
+                &trailhead.capacity.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        timings.record("output", start);
+
+        return Ok(());
+    }
+
+    // regions without any selected trailheads are not fetched
     let regions = trailheads
         .values()
-        .filter_map(|trailhead| trailhead.region.clone())
-        .collect::<HashSet<String>>();
+        .filter(|trailhead| command.includes(trailhead))
+        .filter_map(|trailhead| trailhead.report_region().map(str::to_string))
+        .collect::<BTreeSet<String>>();
 
-    let reports = futures::future::join_all(
-        regions
-            .iter()
-            .map(|region| client.fetch_report(region.as_str())),
-    )
+    // recording needs every report, so runs with a store are written all at once
+    if let Command::Availability(availability) = &command {
+        if availability.streamed() && opts.store.is_none() {
+            let mut out: Box<dyn Write> = match &availability.output {
+                Some(path) => Box::new(fs::File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            let client = &client;
+            let fetch = |region: String| async move { client.fetch_report(&region).await };
+            return stream_jsonl(
+                fetch,
+                &regions,
+                &trailheads,
+                availability,
+                &mut out,
+                timings,
+            )
+            .await;
+        }
+    }
+
+    let fetched = client.fetched().unwrap_or_else(Utc::now);
+    let client = &client;
+    let reports = futures::future::join_all(regions.iter().map(|region| async move {
+        let start = Instant::now();
+        let report = client.fetch_report(region.as_str()).await;
+        (region, start.elapsed(), report)
+    }))
     .await;
 
-    let mut result = BTreeMap::<NaiveDate, BTreeMap<String, u8>>::new();
+    for (region, elapsed, _) in &reports {
+        timings.add(format!("report {}", region), *elapsed);
+    }
+    let start = timings.record("reports", start);
 
-    let now = Utc::now().with_timezone(&Pacific).date().naive_local();
+    if let Command::Trips(Trips { donohue: true, .. }) = &command {
+        let report = reports
+            .iter()
+            .find(|(region, _, _)| region.as_str() == trips::DONOHUE_REGION)
+            .and_then(|(_, _, report)| report.as_ref().ok());
+        trips::check_donohue_report(report.map(Vec::as_slice))?;
+    }
 
-    reports
+    let reports = reports
         .into_iter()
-        .filter_map(|result| result.ok())
+        .filter_map(|(_, _, result)| result.ok())
         .flatten()
-        .flat_map(|report| {
-            let date = report.date;
-            report
-                .values
-                .into_iter()
-                .map(move |(id, occupancy)| (date.clone(), id, occupancy))
-        })
-        .filter_map(|(date, id, occupancy)| {
-            // there are some unlisted trailheads... no name or capacity, we can ignore them
-            let trailhead = trailheads.get(id.as_str())?;
+        .collect::<Vec<_>>();
 
-            // adjust capacity based on the 15 day walk up period in 2020
-            let capacity = if date.signed_duration_since(now).num_days() > 15 {
-                trailhead.quota
-            } else {
-                trailhead.capacity
+    // replaying a stored snapshot would only record it again
+    let start = match &opts.store {
+        Some(path) if client.fetched().is_none() => {
+            Store::open(path)?.record(fetched, client.url(), &trailheads, &reports)?;
+            timings.record("store", start)
+        }
+        _ => start,
+    };
+
+    if let Command::Report(RawReport {
+        format: ReportFormat::Csv,
+        ..
+    }) = command
+    {
+        let mut writer = csv::Writer::from_writer(io::stdout());
+        writer.write_record(["date", "id", "occupancy"])?;
+        // reports come per region, put them in the same order as every other output
+        let mut values = reports
+            .into_iter()
+            .flat_map(|report| {
+                let date = report.date;
+                report
+                    .values
+                    .into_iter()
+                    .map(move |(id, occupancy)| (date, id, occupancy))
+            })
+            .collect::<Vec<_>>();
+        values.sort_by(|(a_date, a_id, _), (b_date, b_id, _)| {
+            let key = |id: &str| trailheads.get(id).map(Trailhead::sort_key);
+            (a_date, key(a_id), a_id).cmp(&(b_date, key(b_id), b_id))
+        });
+        for value in values {
+            writer.serialize(value)?;
+        }
+        writer.flush()?;
+        timings.record("output", start);
+
+        return Ok(());
+    }
+
+    let now = today();
+    let selection = match &command {
+        Command::Availability(availability) => &availability.selection,
+        Command::Compare(compare) => &compare.selection,
+        Command::Export(export) => &export.selection,
+        Command::Report(report) => &report.selection,
+        Command::Trips(trips) => &trips.selection,
+        Command::CompareBaseline(baseline) => &baseline.selection,
+        Command::Diff(_) => unreachable!("handled above"),
+        Command::History(_) => unreachable!("handled above"),
+        Command::Hot(_) => unreachable!("handled above"),
+        Command::Trailheads(_) => unreachable!("handled above"),
+    };
+
+    let mut rows = AvailabilitySet::from_reports(
+        reports,
+        &trailheads,
+        now,
+        selection.overbooked,
+        selection.include_full,
+    );
+
+    // exits are looked up before the selection narrows the rows down to entries
+    let exits = match &command {
+        Command::Trips(trips) => trips.exits(rows.rows()),
+        _ => Exits::default(),
+    };
+    // the baseline is compared before the selection narrows the rows down
+    if let Command::CompareBaseline(baseline) = &command {
+        let start = timings.record("aggregation", start);
+        let published = Mirror::fetch(&baseline.url, now).await?;
+        let reports = regions.iter().flat_map(|region| published.report(region));
+        let published = AvailabilitySet::from_reports(
+            reports,
+            &published.trailheads,
+            now,
+            Overbooking::Clamp,
+            false,
+        );
+        let start = timings.record("baseline", start);
+
+        let changes = diff::changes_from_baseline(&published, &rows);
+        print!(
+            "{}",
+            render_changes(&changes, &baseline.selection, ["baseline", "live"])?
+        );
+        timings.record("output", start);
+
+        return Ok(());
+    }
+
+    rows.retain(|row| selection.matches(row, now));
+    let start = timings.record("aggregation", start);
+
+    let output = match &command {
+        Command::Export(export) => {
+            let provenance = export::Provenance {
+                source: client.url(),
+                fetched,
             };
+            match export.format {
+                ExportFormat::Xlsx => {
+                    let matrices = matrix::by_region(rows);
+                    export::xlsx(
+                        &matrices,
+                        selection.region_names,
+                        provenance,
+                        &export.output,
+                    )?
+                }
+                ExportFormat::Parquet => {
+                    export::parquet(&rows, selection.region_names, provenance, &export.output)?
+                }
+            }
 
-            // sometimes they are overbooked, restrict the range
-            let availability = capacity - min(capacity, occupancy);
+            if let Some(key) = &export.sign_key {
+                let password = export.sign_password.clone();
+                export::sign(&export.output, key, password, provenance)?;
+            }
+            timings.record("output", start);
 
-            // discard full trailheads
-            if availability > 0 {
-                Some((date, trailhead.name.clone(), availability))
-            } else {
-                None
+            return Ok(());
+        }
+        Command::Compare(compare) => render_compare(compare, rows)?,
+        Command::Trips(trips) => render_trips(trips, rows, &exits)?,
+        Command::CompareBaseline(_) => unreachable!("handled above"),
+        Command::Diff(_) => unreachable!("handled above"),
+        Command::History(_) => unreachable!("handled above"),
+        Command::Hot(_) => unreachable!("handled above"),
+        Command::Report(report) => {
+            let matrices = matrix::by_region(rows);
+            match report.format {
+                ReportFormat::Html => markup::html(&matrices, now),
+                ReportFormat::Md => markup::markdown(&matrices),
+                ReportFormat::Csv => unreachable!("handled above"),
             }
+        }
+        Command::Availability(availability) if availability.interactive => {
+            let stdin = io::stdin();
+            repl::run(
+                &rows,
+                selection.region_names,
+                selection.overbooked,
+                stdin.lock(),
+                io::stdout(),
+            )?;
+            timings.record("output", start);
+
+            return Ok(());
+        }
+        Command::Availability(availability) if availability.format() == Format::Influx => {
+            if availability.summary.is_some() {
+                return Err("summaries can't be written as influx lines".into());
+            }
+            // stamped with the fetch time, which render doesn't know
+            influx::lines(rows.rows(), fetched)
+        }
+        Command::Availability(availability) => render(availability, rows, now)?,
+        Command::Trailheads(_) => unreachable!("handled above"),
+    };
+
+    match &command {
+        Command::Availability(
+            availability @ Availability {
+                influx_url: Some(url),
+                ..
+            },
+        ) if availability.format() == Format::Influx => {
+            let token = availability.influx_token.as_deref();
+            influx::push(url, token, output.clone()).await?
+        }
+        Command::Availability(Availability {
+            output: Some(path), ..
+        }) => fs::write(path, &output)?,
+        _ => print!("{}", output),
+    }
+
+    if let Command::Availability(availability) = &command {
+        if availability.copy {
+            clipboard::copy(&output)?;
+        }
+    }
+    timings.record("output", start);
+
+    Ok(())
+}
+
+/// Writes each region's rows as JSON lines as soon as its report and those of the regions
+/// before it have arrived. Lines come out region by region, ordered by region name, then by
+/// date and trailhead name like the other formats.
+async fn stream_jsonl<F, R>(
+    fetch: F,
+    regions: &BTreeSet<String>,
+    trailheads: &BTreeMap<String, Trailhead>,
+    opts: &Availability,
+    out: &mut dyn Write,
+    timings: &mut Timings,
+) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(String) -> R,
+    R: Future<Output = Result<Vec<ReportDate>, Box<dyn Error>>>,
+{
+    let start = Instant::now();
+    let now = today();
+    let columns = opts.jsonl_columns();
+
+    // regions are fetched together, but written in order
+    let mut regions = regions.iter().collect::<Vec<_>>();
+    regions.sort_by_key(|region| regions::name(region));
+    let fetch = &fetch;
+    let mut reports = regions
+        .into_iter()
+        .map(|region| async move {
+            let start = Instant::now();
+            let report = fetch(region.clone()).await;
+            (region, start.elapsed(), report)
         })
-        .for_each(|(date, trailhead, availability)| {
-            result
-                .entry(date)
-                .or_insert(BTreeMap::new())
-                .insert(trailhead, availability);
-        });
+        .collect::<FuturesOrdered<_>>();
+
+    while let Some((region, elapsed, report)) = reports.next().await {
+        timings.add(format!("report {}", region), elapsed);
+        // like the other formats, regions that fail to load are skipped
+        let report = match report {
+            Ok(report) => report,
+            Err(_) => continue,
+        };
 
-    for (date, values) in result {
-        for (th, a) in values {
-            println!("{},{},{}", date, th, a);
+        let mut rows = AvailabilitySet::from_reports(
+            report,
+            trailheads,
+            now,
+            opts.selection.overbooked,
+            opts.selection.include_full,
+        );
+        rows.retain(|row| opts.selection.matches(row, now));
+
+        for row in rows.rows() {
+            writeln!(out, "{}", json_record(&columns, row, opts, now))?;
         }
+        out.flush()?;
     }
+    // lines are written as the reports arrive, so this includes waiting for them
+    timings.record("output", start);
 
     Ok(())
 }
 
+fn json_record(
+    columns: &[ColumnSpec],
+    row: &AvailabilityRow,
+    opts: &Availability,
+    now: NaiveDate,
+) -> serde_json::Value {
+    let region_names = opts.selection.region_names;
+    let mut record = columns
+        .iter()
+        .map(|spec| {
+            let value = spec.column.json(
+                row,
+                region_names,
+                opts.selection.overbooked,
+                opts.cost(row, now),
+            );
+            (spec.header.clone(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    record.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+
+    serde_json::Value::Object(record)
+}
+
+/// A pretty printed JSON array, or one compact value per line for JSON lines.
+fn json_output(
+    format: Format,
+    values: impl Iterator<Item = serde_json::Value>,
+) -> Result<String, Box<dyn Error>> {
+    if format == Format::Jsonl {
+        Ok(values.map(|value| value.to_string() + "\n").collect())
+    } else {
+        Ok(serde_json::to_string_pretty(&values.collect::<Vec<_>>())? + "\n")
+    }
+}
+
+/// Formats each trailhead's stats for both windows.
+fn render_compare(compare: &Compare, rows: AvailabilitySet) -> Result<String, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "name",
+        "a_open_days",
+        "a_max_availability",
+        "b_open_days",
+        "b_max_availability",
+    ])?;
+
+    for (_, (trailhead, a, b)) in compare::compare(compare, rows) {
+        writer.serialize((
+            display_name(trailhead, compare.selection.region_names),
+            a.open_days,
+            a.max_availability,
+            b.open_days,
+            b.max_availability,
+        ))?;
+    }
+
+    csv_string(writer)
+}
+
+fn render_trips(
+    trips: &Trips,
+    rows: AvailabilitySet,
+    exits: &Exits,
+) -> Result<String, Box<dyn Error>> {
+    let region_names = trips.selection.region_names;
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut header = vec![
+        "start",
+        "end",
+        "entry",
+        "availability",
+        "exit",
+        "exit_availability",
+    ];
+    if trips.donohue {
+        header.push("donohue_availability");
+    }
+    if trips.blocked {
+        header.push("blocked_by");
+    }
+    writer.write_record(header)?;
+
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    for trip in trips.trips(rows, exits) {
+        let mut record = vec![
+            trip.entry.date.to_string(),
+            trip.end.to_string(),
+            display_name(trip.entry.trailhead, region_names),
+            trip.entry.availability.to_string(),
+            optional(
+                trip.exit
+                    .map(|exit| display_name(exit.trailhead, region_names)),
+            ),
+            optional(trip.exit.map(|exit| exit.availability.to_string())),
+        ];
+        if trips.donohue {
+            record.push(optional(
+                trip.donohue.map(|donohue| donohue.availability.to_string()),
+            ));
+        }
+        if trips.blocked {
+            let blocked = trip.blocked.iter().map(ToString::to_string);
+            record.push(blocked.collect::<Vec<_>>().join("; "));
+        }
+        writer.write_record(record)?;
+    }
+
+    csv_string(writer)
+}
+
+/// Formats each change between the two snapshots.
+fn render_diff(diff: &Diff, store: &Store) -> Result<String, Box<dyn Error>> {
+    let (before, after) = diff.snapshots(store)?;
+    let (before, after) = (store.load(before)?, store.load(after)?);
+    let (before, after) = (before.rows(), after.rows());
+
+    let changes = diff::changes(&before, &after);
+    render_changes(&changes, &diff.selection, ["before", "after"])
+}
+
+/// Formats each trailhead and date's open spots per snapshot, oldest first.
+fn render_history(history: &History, store: &Store) -> Result<String, Box<dyn Error>> {
+    let snapshots = store.snapshots()?;
+    let region_names = history.selection.region_names;
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["date", "name", "fetched", "availability"])?;
+
+    for series in history::series(&snapshots, &history.selection, today()) {
+        for (fetched, row) in series {
+            writer.serialize((
+                row.date,
+                display_name(row.trailhead, region_names),
+                fetched.to_rfc3339_opts(SecondsFormat::Secs, true),
+                row.availability,
+            ))?;
+        }
+    }
+
+    csv_string(writer)
+}
+
+/// Formats the trailheads that fill fastest first, with the median hours they took.
+fn render_hot(hot: &Hot, store: &Store) -> Result<String, Box<dyn Error>> {
+    let snapshots = store.snapshots()?;
+    let series = history::series(&snapshots, &hot.selection, today());
+    let latest = snapshots.last().map(|snapshot| snapshot.fetched);
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["name", "hours_to_fill", "filled", "percent_full"])?;
+    for heat in hot::rank(&series, latest) {
+        let hours = heat
+            .minutes_to_fill
+            .map(|minutes| format!("{:.1}", minutes as f64 / 60.0));
+        writer.serialize((
+            display_name(heat.trailhead, hot.selection.region_names),
+            hours,
+            heat.filled,
+            heat.percent_full,
+        ))?;
+    }
+
+    csv_string(writer)
+}
+
+/// Formats each change with the spots on either side, under the given headers.
+fn render_changes(
+    changes: &[Change],
+    selection: &Selection,
+    sides: [&str; 2],
+) -> Result<String, Box<dyn Error>> {
+    let now = today();
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["date", "name", sides[0], sides[1], "change", "transition"])?;
+
+    // a change counts when either side is selected, so spots going to zero still show
+    for change in changes.iter().filter(|change| {
+        selection.matches(&change.before, now) || selection.matches(&change.after, now)
+    }) {
+        writer.serialize((
+            change.after.date,
+            display_name(change.after.trailhead, selection.region_names),
+            change.before.availability,
+            change.after.availability,
+            change.delta(),
+            change.transition().name(),
+        ))?;
+    }
+
+    csv_string(writer)
+}
+
+fn csv_string(writer: csv::Writer<Vec<u8>>) -> Result<String, Box<dyn Error>> {
+    let bytes = writer.into_inner().map_err(|err| err.into_error())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Formats the rows as a summary or one entry per row, in the requested format.
+fn render(
+    opts: &Availability,
+    rows: AvailabilitySet,
+    now: NaiveDate,
+) -> Result<String, Box<dyn Error>> {
+    let region_names = opts.selection.region_names;
+
+    if let Some(summary) = opts.summary {
+        let totals = summary::summarize(summary, rows);
+        return match opts.format() {
+            Format::Table => Ok(table::summary(&totals, opts.styled())),
+            Format::Csv => {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(["date", "region", "total", "max"])?;
+                for ((date, region), total) in totals {
+                    writer.serialize((date, region, total.sum, total.max))?;
+                }
+                csv_string(writer)
+            }
+            Format::Json | Format::Jsonl => {
+                let totals = totals.into_iter().map(|((date, region), total)| {
+                    serde_json::json!({
+                        "schema_version": SCHEMA_VERSION,
+                        "date": date,
+                        "region": region,
+                        "total": total.sum,
+                        "max": total.max,
+                    })
+                });
+                json_output(opts.format(), totals)
+            }
+            Format::Influx => unreachable!("written before rendering"),
+            Format::Ics => {
+                let mut days = BTreeMap::<_, Vec<_>>::new();
+                for ((date, region), total) in totals {
+                    days.entry(date)
+                        .or_default()
+                        .push((region.to_string(), total.sum));
+                }
+                Ok(ics::calendar(&days, Utc::now()))
+            }
+        };
+    }
+
+    let mut rows = rows.into_iter().collect::<Vec<_>>();
+    if opts.sort_by.is_some() || opts.desc {
+        let column = opts.sort_by.unwrap_or(Column::Date);
+        columns::sort_ordered(&mut rows, opts.desc, |rows| match column {
+            Column::Cost => rows.sort_by_key(|row| opts.cost(row, now).map(|cost| cost.0)),
+            column => column.sort(rows, opts.selection.overbooked),
+        });
+    }
+    // JSON lines are streamed region by region, and come out the same way when buffered
+    let region_major = opts.format() == Format::Jsonl && opts.sort_by.is_none() && !opts.desc;
+    if opts.group_by_region || region_major {
+        // a stable sort keeps the date and name order within each region
+        rows.sort_by_key(|row| row.trailhead.region_name());
+    }
+
+    let format = opts.format();
+    if format == Format::Table {
+        return Ok(table::availability(&matrix::by_region(rows), opts.styled()));
+    }
+
+    if format == Format::Ics {
+        let mut days = BTreeMap::<_, Vec<_>>::new();
+        // overbooked rows have nothing to offer on the calendar
+        for row in rows.iter().filter(|row| row.availability > 0) {
+            let name = display_name(row.trailhead, region_names);
+            days.entry(row.date)
+                .or_default()
+                .push((name, u32::from(row.availability)));
+        }
+        return Ok(ics::calendar(&days, Utc::now()));
+    }
+
+    if format == Format::Json && opts.columns.is_none() && !opts.percent_full {
+        return Ok(serde_json::to_string_pretty(&JsonAvailability::new(
+            &rows,
+            opts.selection.overbooked,
+        ))? + "\n");
+    }
+
+    if format == Format::Json || format == Format::Jsonl {
+        let columns = match format {
+            Format::Jsonl => opts.jsonl_columns(),
+            _ => opts.columns(),
+        };
+        let records = rows.iter().map(|row| json_record(&columns, row, opts, now));
+        return json_output(format, records);
+    }
+
+    let columns = opts.columns();
+    let cost = |row: &AvailabilityRow| opts.cost(row, now);
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns.iter().map(|spec| spec.header.as_str()))?;
+
+    for row in &rows {
+        writer.write_record(columns.iter().map(|spec| {
+            spec.column
+                .value(row, region_names, opts.selection.overbooked, cost(row))
+        }))?;
+    }
+
+    csv_string(writer)
+}
+
+/// Today's date in the park's timezone.
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Pacific).date_naive()
+}
+
+/// Trailheads by region name, then name and id, rather than by id.
+fn sorted(trailheads: &BTreeMap<String, Trailhead>) -> impl Iterator<Item = &Trailhead> {
+    let mut sorted = trailheads.values().collect::<Vec<_>>();
+    sorted.sort_by_key(|trailhead| trailhead.sort_key());
+    sorted.into_iter()
+}
+
+fn display_name(trailhead: &Trailhead, region_names: bool) -> String {
+    match trailhead.region_name() {
+        Some(region) if region_names => format!("{} / {}", region, trailhead.name),
+        _ => trailhead.name.clone(),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct Status {
     r#type: String,
@@ -213,15 +1359,67 @@ struct Response<T> {
     response: T,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Trailhead {
     id: String,
     name: String,
     region: Option<String>,
     quota: u8,
     capacity: u8,
+    description: Option<String>,
     alert: Option<String>,
     notes: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+impl Trailhead {
+    fn region_name(&self) -> Option<&str> {
+        self.region.as_deref().map(regions::name)
+    }
+
+    /// Orders by region name, then name and id. Names compare byte by byte, so the order
+    /// does not depend on the locale.
+    fn sort_key(&self) -> (Option<&str>, &str, &str) {
+        (self.region_name(), self.name.as_str(), self.id.as_str())
+    }
+
+    /// The region whose report carries the trailhead. The Donohue Pass exits have no region
+    /// of their own and are assumed to come with `trips::DONOHUE_REGION`.
+    fn report_region(&self) -> Option<&str> {
+        match self.region.as_deref() {
+            None if trips::is_donohue_exit(self) => Some(trips::DONOHUE_REGION),
+            region => region,
+        }
+    }
+
+    /// Whether `region` is the trailhead's region code or loosely matches its name.
+    fn in_region(&self, region: &str) -> bool {
+        let code = self.region.as_deref().unwrap_or_default();
+        code.eq_ignore_ascii_case(region)
+            || self
+                .region_name()
+                .is_some_and(|name| fuzzy::matches(region, name))
+    }
+}
+
+#[cfg(test)]
+impl Trailhead {
+    /// A trailhead with a quota of 6 and capacity of 10 and nothing else set.
+    fn test(id: &str, name: &str, region: &str) -> Trailhead {
+        Trailhead {
+            id: id.to_string(),
+            name: name.to_string(),
+            region: Some(region.to_string()),
+            quota: 6,
+            capacity: 10,
+            description: None,
+            alert: None,
+            notes: None,
+            latitude: None,
+            longitude: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -243,19 +1441,319 @@ struct Report {
     values: Vec<BTreeMap<String, ReportValue>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct ReportDate {
     date: NaiveDate,
     values: BTreeMap<String, u8>,
 }
 
+impl ReportDate {
+    /// The same date, limited to the listed trailheads in `region`.
+    fn in_region(&self, trailheads: &BTreeMap<String, Trailhead>, region: &str) -> ReportDate {
+        let values = self
+            .values
+            .iter()
+            .filter(|(id, _)| {
+                let trailhead = trailheads.get(id.as_str());
+                trailhead.and_then(Trailhead::report_region) == Some(region)
+            })
+            .map(|(id, &occupancy)| (id.clone(), occupancy))
+            .collect();
+
+        ReportDate {
+            date: self.date,
+            values,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Report, Response, Trailheads};
+    use crate::availability::{AvailabilityRow, AvailabilitySet, Overbooking};
+    use crate::{
+        common_headers, render, stream_jsonl, Opts, Report, ReportDate, Response, Timings,
+        Trailhead, Trailheads, YoseClient,
+    };
+    use chrono::{Datelike, NaiveDate};
+    use clap::{CommandFactory, Parser};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
 
     #[test]
-    fn parse_trailheads() {
-        let test = r#"{"status":
+    fn valid_arguments() {
+        // catches flags that clash across flattened option groups
+        Opts::command().debug_assert();
+    }
+
+    #[test]
+    fn reject_invalid_cookies() {
+        assert!(common_headers("a=b; c=d").is_ok());
+        assert!(common_headers("  ").is_err());
+        assert!(common_headers("a=b\nc=d").is_err());
+    }
+
+    #[test]
+    fn render_csv_with_header() {
+        let trailhead = Trailhead::test("b12a", "Tioga Road, White Wolf", "bf");
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let rows = || {
+            vec![AvailabilityRow::new(date, &trailhead, 4)]
+                .into_iter()
+                .collect::<AvailabilitySet>()
+        };
+
+        let opts = Opts::parse_from(["yose", "--format", "csv"]);
+        assert_eq!(
+            render(&opts.availability, rows(), date).unwrap(),
+            "date,name,availability\n2020-09-10,\"Tioga Road, White Wolf\",4\n"
+        );
+
+        let opts = Opts::parse_from(["yose", "--columns", "id,availability=open"]);
+        assert_eq!(
+            render(&opts.availability, rows(), date).unwrap(),
+            "id,open\nb12a,4\n"
+        );
+
+        let later = date.succ_opt().unwrap();
+        let sorted = |args: &[&str]| {
+            let rows = vec![
+                AvailabilityRow::new(date, &trailhead, 4),
+                AvailabilityRow::new(later, &trailhead, 9),
+            ];
+            let opts = Opts::parse_from(args);
+            let rows = rows.into_iter().collect::<AvailabilitySet>();
+            render(&opts.availability, rows, date).unwrap()
+        };
+        let args = ["yose", "--format", "csv", "--columns", "availability"];
+        assert_eq!(sorted(&args), "availability\n4\n9\n");
+        let args = [&args[..], &["--sort-by", "availability", "--desc"]].concat();
+        assert_eq!(sorted(&args), "availability\n9\n4\n");
+
+        let opts = Opts::parse_from(["yose", "--percent-full"]);
+        assert_eq!(
+            render(&opts.availability, rows(), date).unwrap(),
+            "date,name,percent_full,occupancy,limit\n\
+             2020-09-10,\"Tioga Road, White Wolf\",60,6,10\n"
+        );
+
+        let opts = Opts::parse_from(["yose", "--format", "jsonl"]);
+        assert_eq!(
+            render(&opts.availability, rows(), date).unwrap(),
+            concat!(
+                r#"{"availability":4,"date":"2020-09-10","id":"b12a","#,
+                r#""name":"Tioga Road, White Wolf","region":"Big Oak Flat & Tioga Road","#,
+                r#""schema_version":1}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn overbooked_rows_agree_across_formats() {
+        let trailhead = Trailhead::test("b12a", "White Wolf", "bf");
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let render = |args: &[&str]| {
+            let args = [
+                &["yose", "--include-full", "--columns", "availability"],
+                args,
+            ]
+            .concat();
+            let opts = Opts::parse_from(args);
+            let rows = vec![AvailabilityRow::from_occupancy(date, &trailhead, 12, 10)];
+            let rows = rows.into_iter().collect::<AvailabilitySet>();
+            render(&opts.availability, rows, date).unwrap()
+        };
+
+        assert_eq!(render(&["--format", "csv"]), "availability\n0\n");
+        assert_eq!(
+            render(&["--format", "jsonl"]),
+            "{\"availability\":0,\"schema_version\":1}\n"
+        );
+        let row = AvailabilityRow::from_occupancy(date, &trailhead, 12, 10);
+        let matches = |args: &[&str]| {
+            let opts =
+                Opts::parse_from([&["yose", "--filter", "availability == 0"], args].concat());
+            opts.availability.selection.matches(&row, date)
+        };
+        assert!(matches(&[]));
+        assert!(!matches(&["--overbooked", "negative"]));
+        assert_eq!(
+            render(&["--format", "csv", "--overbooked", "negative"]),
+            "availability\n-2\n"
+        );
+        assert_eq!(
+            render(&["--format", "jsonl", "--overbooked", "negative"]),
+            "{\"availability\":-2,\"schema_version\":1}\n"
+        );
+    }
+
+    #[test]
+    fn select_rows() {
+        let trailhead = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let today = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let dates = |args: &[&str]| {
+            let opts = Opts::parse_from(args);
+            (10..=14)
+                .map(|day| NaiveDate::from_ymd_opt(2020, 9, day).unwrap())
+                .filter(|&date| {
+                    let row = AvailabilityRow::new(date, &trailhead, 1);
+                    opts.availability.selection.matches(&row, today)
+                })
+                .map(|date| date.day())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(dates(&["yose"]), vec![10, 11, 12, 13, 14]);
+        assert_eq!(
+            dates(&["yose", "--from", "2020-09-11", "--to", "2020-09-13"]),
+            vec![11, 12, 13]
+        );
+        assert_eq!(dates(&["yose", "--to", "2020-09-11"]), vec![10, 11]);
+        assert_eq!(dates(&["yose", "--next-days", "3"]), vec![10, 11, 12]);
+        assert!(Opts::try_parse_from(["yose", "--next-days", "3", "--to", "sep-20"]).is_err());
+
+        assert!(dates(&["yose", "--trailhead", "lyell"]).is_empty());
+        assert_eq!(
+            dates(&["yose", "--trailhead", "lyell", "--trailhead", "cathedral"]).len(),
+            5
+        );
+        assert_eq!(dates(&["yose", "--region", "tm,valley"]).len(), 5);
+        assert!(dates(&["yose", "--region", "valley"]).is_empty());
+        assert_eq!(dates(&["yose", "--min-availability", "1"]).len(), 5);
+        assert!(dates(&["yose", "--party-size", "2"]).is_empty());
+
+        assert_eq!(dates(&["yose", "--dow", "fri,Saturday"]), vec![11, 12]);
+        assert_eq!(dates(&["yose", "--weekends"]), vec![12, 13]);
+        assert_eq!(dates(&["yose", "--weekdays"]), vec![10, 11, 14]);
+        assert!(Opts::try_parse_from(["yose", "--dow", "someday"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_jsonl_in_region_order() {
+        let trailheads = vec![
+            Trailhead::test("b11", "Aspen Valley", "bf"),
+            Trailhead::test("t24b", "Lyell Canyon", "tm"),
+            Trailhead::test("w32", "Bridalveil Creek", "ww"),
+        ]
+        .into_iter()
+        .map(|trailhead| (trailhead.id.clone(), trailhead))
+        .collect::<BTreeMap<_, _>>();
+        let regions = ["bf", "tm", "ww"]
+            .iter()
+            .map(|region| region.to_string())
+            .collect::<BTreeSet<_>>();
+
+        // the first region answers last, and Glacier Point & Wawona comes before Tuolumne
+        // Meadows by name
+        let trailheads = &trailheads;
+        let report = |region: &str| {
+            (10..=11)
+                .map(|day| ReportDate {
+                    date: NaiveDate::from_ymd_opt(2030, 9, day).unwrap(),
+                    values: trailheads
+                        .values()
+                        .filter(|trailhead| trailhead.region.as_deref() == Some(region))
+                        .map(|trailhead| (trailhead.id.clone(), 2))
+                        .collect(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let fetch = |region: String| async move {
+            if region == "bf" {
+                let () = tokio::task::yield_now().await;
+            }
+            Ok(report(&region))
+        };
+
+        let opts = Opts::parse_from(["yose", "--format", "jsonl", "--columns", "id,date"]);
+        let mut out = Vec::new();
+        let mut timings = Timings::default();
+        stream_jsonl(
+            fetch,
+            &regions,
+            trailheads,
+            &opts.availability,
+            &mut out,
+            &mut timings,
+        )
+        .await
+        .unwrap();
+        let streamed = String::from_utf8(out).unwrap();
+        assert_eq!(
+            streamed,
+            concat!(
+                r#"{"date":"2030-09-10","id":"b11","schema_version":1}"#,
+                "\n",
+                r#"{"date":"2030-09-11","id":"b11","schema_version":1}"#,
+                "\n",
+                r#"{"date":"2030-09-10","id":"w32","schema_version":1}"#,
+                "\n",
+                r#"{"date":"2030-09-11","id":"w32","schema_version":1}"#,
+                "\n",
+                r#"{"date":"2030-09-10","id":"t24b","schema_version":1}"#,
+                "\n",
+                r#"{"date":"2030-09-11","id":"t24b","schema_version":1}"#,
+                "\n"
+            )
+        );
+
+        let steps = timings.steps().collect::<Vec<_>>();
+        assert_eq!(steps, ["report bf", "report ww", "report tm", "output"]);
+
+        // the buffered output has the same lines in the same order
+        let today = crate::today();
+        let reports = regions.iter().flat_map(|region| report(region));
+        let rows =
+            AvailabilitySet::from_reports(reports, trailheads, today, Overbooking::Clamp, false);
+        assert_eq!(render(&opts.availability, rows, today).unwrap(), streamed);
+    }
+
+    #[tokio::test]
+    async fn decode_gzip_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+
+            let body = r#"{"status":{"type":"message","value":"trailheads found."},
+                "response":{"timestamp":"2020-09-09T13:12:44","values":{}}}"#;
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body.as_bytes()).unwrap();
+            let body = encoder.finish().unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+
+            String::from_utf8(request).unwrap().to_lowercase()
+        });
+
+        let mut client = YoseClient::new("a=b").unwrap();
+        client.url = format!("http://{}/query.php", address);
+        let trailheads = client.fetch_trailheads().await.unwrap();
+        assert!(trailheads.values.is_empty());
+
+        let request = server.join().unwrap();
+        assert!(request.contains("accept-encoding: gzip, br"));
+    }
+
+    /// A trailheads response from the site.
+    pub(crate) const TRAILHEADS: &str = r#"{"status":
             {"type":"message","value":"trailheads found."},
             "response":{
                 "timestamp":"2020-09-06T22:43:55",
@@ -265,7 +1763,10 @@ mod tests {
                         "b11":{"id":"b11","name":"Aspen Valley","wpsName":"Aspen Valley","region":"bf","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>This trail is not used often and portions of the trail are overgrown with vegetation. Bring a good map of the area.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300' from water.<\/li>"},"h29b":{"id":"h29b","name":"Beehive Meadow","wpsName":"Beehive Meadow","region":"hh","latitude":null,"longitude":null,"description":null,"quota":21,"capacity":35,"alert":null,"notes":null},"w32":{"id":"w32","name":"Bridalveil Creek","wpsName":"Bridalveil Creek","region":"ww","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>You may not camp at Bridalveil Creek Campground with this permit.<\/li>"},"x03":{"id":"x03","name":"Budd Creek (cross-country only)","wpsName":"Budd Creek (cross-country only)","region":"tm","latitude":null,"longitude":null,"description":null,"quota":3,"capacity":5,"alert":"This is a cross-country trailhead, and the trail\/route is not maintained. All members of the party must be proficient at backcountry navigation.","notes":"<li>Camping is prohibited in the Budd Lake and Elizabeth Lake drainages.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t21":{"id":"t21","name":"Cathedral Lakes","wpsName":"Cathedral Lakes","region":"tm","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>Fires are prohibited at Upper and Lower Cathedral Lakes.<\/li><li>Camping is prohibited in the Budd Lake and Elizabeth Lake drainages.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"w36":{"id":"w36","name":"Chilnualna Falls","wpsName":"Chilnualna Falls","region":"ww","latitude":null,"longitude":null,"description":null,"quota":24,"capacity":40,"alert":"This trailhead is currently <b>closed<\/b> due to the <span><a href=\"https:\/\/inciweb.nwcg.gov\/incident\/7147\/\" target=\"_blank\">Creek Fire<\/a>.<\/span>","notes":"<li>Only use existing fire rings. Building new fire rings is not allowed.<\/li><li>You must be at the top of Chilnualna Falls before camping.<\/li>"},"h26a":{"id":"h26a","name":"Cottonwood Creek","wpsName":"Cottonwood Creek","region":"hh","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":20,"alert":null,"notes":"<li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"w30":{"id":"w30","name":"Deer Camp","wpsName":"Deer Camp","region":"ww","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":"This trailhead is currently <b>closed<\/b> due to the <span><a href=\"https:\/\/inciweb.nwcg.gov\/incident\/7147\/\" target=\"_blank\">Creek Fire<\/a>.<\/span>","notes":null},"d01":{"id":"d01","name":"Donohue Exit 1","wpsName":"DonohueValley","region":null,"latitude":null,"longitude":null,"description":null,"quota":20,"capacity":20,"alert":null,"notes":null},"d02":{"id":"d02","name":"Donohue Exit 2","wpsName":"DonohueLyell","region":null,"latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":null},"x04":{"id":"x04","name":"Gaylor Creek (cross-country only)","wpsName":"Gaylor Creek\/Lake (cross-country only)","region":"tm","latitude":null,"longitude":null,"description":null,"quota":3,"capacity":3,"alert":"This is a cross-country trailhead, and the trail\/route is not maintained. All members of the party must be proficient at backcountry navigation.","notes":"<li>No camping in the park from Great Sierra Mine to White Mountain and all watershed downstream, including Gaylor Lakes.<\/li><li>No camping in the Monroe Hall Research Area.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"w03b":{"id":"w03b","name":"Glacier Point to Illilouette","wpsName":"Glacier Point->Illilouette","region":"ww","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>Please remember you must travel as far as the Buena Vista Trail junction before camping.<\/li><li>You may not camp in Little Yosemite Valley your first night with this permit.<\/li><li>Camping is not allowed along the Panorama Trail or at the top of Illilouette Fall.<\/li>"},"w03a":{"id":"w03a","name":"Glacier Point to Little Yosemite Valley","wpsName":"Glacier Point->Little Yosemite Valley","region":"ww","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass<\/span> or <span>Half Dome Cables.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j03a\">click here<\/a>.","quota":6,"capacity":10,"alert":null,"notes":"<li>You must get to Little Yosemite Valley before camping. You must camp your first night at Little Yosemite Valley.<\/li><li>Bear boxes and composting toilet are available at the campground. Bears have obtained food from backpackers in this area.<\/li>"},"j03a":{"id":"j03a","name":"Glacier Point to Little Yosemite Valley","wpsName":"Glacier Point->Little Yosemite Valley","region":"jm","latitude":null,"longitude":null,"description":"If you do not plan on exiting the park via Donohue Pass, please <span><a href=\"?region=yv&th=y03a\">click here<\/a>.<\/span>","quota":6,"capacity":10,"alert":null,"notes":"<li>You must get to Little Yosemite Valley before camping. You must camp your first night at Little Yosemite Valley.<\/li><li>Bear boxes and composting toilets are there for your use.<\/li>"},"y03a":{"id":"y03a","name":"Glacier Point to Little Yosemite Valley","wpsName":"Glacier Point->Little Yosemite Valley","region":"yv","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass<\/span> or <span>Half Dome Cables.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j03a\">click here<\/a>.","quota":6,"capacity":10,"alert":null,"notes":"<li>You must get to Little Yosemite Valley before camping. You must camp your first night at Little Yosemite Valley.<\/li><li>Bear boxes and composting toilets are there for your use.<\/li>"},"t22a":{"id":"t22a","name":"Glen Aulin","wpsName":"Glen Aulin","region":"tm","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":20,"alert":"The composting toilet at the Glen Aulin campground is not open this year. Please disperse several hundred feet from the campground boundary when going to the bathroom.","notes":"<li>You must camp at the Glen Aulin High Sierra Camp backpackers campground your first night.<\/li><li>Bears are very active and have obtained food from backpackers in this area.<\/li><li>Fires are permitted only in the established community fire rings.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace rules.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t22b":{"id":"t22b","name":"Glen Aulin Pass Thru to Cold Canyon or Waterwheel Falls","wpsName":"Glen Aulin->Cold Canyon\/Waterwheel (pass through)","region":"tm","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":16,"alert":"The composting toilet at the Glen Aulin campground is not open this year. Please disperse several hundred feet from the campground boundary when going to the bathroom.","notes":"<li>You may not camp at the Glen Aulin High Sierra Camp backpackers camp your first night with this permit.<\/li><li>Bears have been successful in getting food from backpackers in this area.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"y01c":{"id":"y01c","name":"Happy Isles to Illilouette","wpsName":"Happy Isles->Illilouette","region":"yv","latitude":null,"longitude":null,"description":null,"quota":3,"capacity":5,"alert":null,"notes":"<li>You may not camp at Little Yosemite Valley your first night with this permit.<\/li>"},"y01b":{"id":"y01b","name":"Happy Isles to Little Yosemite Valley","wpsName":"Happy Isles->Little Yosemite Valley","region":"yv","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass<\/span> or <span>Half Dome Cables.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j01b\">click here<\/a>.","quota":18,"capacity":30,"alert":null,"notes":"<li>You must get to Little Yosemite Valley before camping. You must camp your first night at Little Yosemite Valley.<\/li><li>Bear boxes and composting toilet are available at the campground. Bears have obtained food from backpackers in this area.<\/li>"},"j01b":{"id":"j01b","name":"Happy Isles to Little Yosemite Valley","wpsName":"Happy Isles->Little Yosemite Valley","region":"jm","latitude":null,"longitude":null,"description":"If you do not plan on exiting the park via Donohue Pass, please <span><a href=\"?region=yv&th=y01b\">click here<\/a>.<\/span>","quota":18,"capacity":30,"alert":null,"notes":"<li>You must get to Little Yosemite Valley before camping. You must camp your first night at Little Yosemite Valley.<\/li><li>Bear boxes and composting toilet are available at the campground.<\/li>"},"y01a":{"id":"y01a","name":"Happy Isles to Sunrise\/Merced Lake Pass Thru","wpsName":"Happy Isles->Sunrise\/Merced Lake (pass through)","region":"yv","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass<\/span> or <span>Half Dome Cables.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j01a\">click here<\/a>.","quota":6,"capacity":10,"alert":null,"notes":"<li>You must camp beyond Little Yosemite Valley and Moraine Dome.<\/li><li>Bears have obtained food from backpackers in this area. There are bear lockers at Merced Lake backpackers camp.<\/li>"},"j01a":{"id":"j01a","name":"Happy Isles to Sunrise\/Merced Lake Pass Thru","wpsName":"Happy Isles->Sunrise\/Merced Lake (pass through)","region":"jm","latitude":null,"longitude":null,"description":"If you do not plan on exiting the park via Donohue Pass, please <span><a href=\"?region=yv&th=y01a\">click here<\/a>.<\/span>","quota":6,"capacity":10,"alert":null,"notes":"<li>You must camp beyond Little Yosemite Valley and Moraine Dome.<\/li><li>Bears have obtained food from backpackers in this area. There are bear lockers at Merced Lake backpackers camp.<\/li>"},"b13b":{"id":"b13b","name":"Luken to Luken's Lake","wpsName":"Luken->Lukens Lake","region":"bf","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>Camping is not permitted at Luken's Lake.<\/li>"},"b13a":{"id":"b13a","name":"Luken to Yosemite Creek","wpsName":"Lukens Lake->Yosemite Creek","region":"bf","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":"This trailhead is currently <b>closed<\/b> due to the <span><a href=\"https:\/\/inciweb.nwcg.gov\/incident\/6888\/\" target=\"_blank\">Blue Jay Fire<\/a>.<\/span> <span><a href=\"https:\/\/www.nps.gov\/yose\/learn\/management\/closures.htm#cs_control_6605255\" target=\"_blank\">Learn more<\/a>.<\/span>","notes":"<li>Camp at least one-half mile back from the rim of the Valley.<\/li><li>The area around the top of Yosemite Falls is for day use only.<\/li>"},"j24b":{"id":"j24b","name":"Lyell Canyon","wpsName":"Lyell Canyon","region":"jm","latitude":null,"longitude":null,"description":"If you do not plan on exiting the park via Donohue Pass, please <span><a href=\"?region=tm&th=t24b\">click here<\/a>.<\/span>","quota":21,"capacity":35,"alert":null,"notes":"<li>Travel at least four miles out Lyell Canyon before camping.<\/li><li>Bears have been successful in getting food from backpackers in this area. Bear canisters are required.<\/li><li>No fires above 9,600 feet.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace rules.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t24b":{"id":"t24b","name":"Lyell Canyon","wpsName":"Lyell Canyon","region":"tm","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j24b\">click here<\/a>.","quota":21,"capacity":35,"alert":null,"notes":"<li>Travel at least four miles out Lyell Canyon before camping.<\/li><li>Bears have been successful in getting food from backpackers in this area. Bear canisters are required.<\/li><li>No fires above 9,600 feet.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace rules.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"h26b":{"id":"h26b","name":"Mather Ranger Station","wpsName":"Mather Ranger Station","region":"hh","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":null},"b17":{"id":"b17","name":"May Lake","wpsName":"May Lake","region":"bf","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>All food and toiletries must be stored in proper food storage containers.<\/li><li>Only use existing fire rings.<\/li><li>Pack out all trash, including toilet paper.<\/li>"},"b16":{"id":"b16","name":"May Lake to Snow Creek","wpsName":"May Lake->Snow Creek","region":"bf","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":"An area closure is in effect to the south and east of the Snow Creek footbridge. <span><a href=\"https:\/\/www.nps.gov\/yose\/learn\/management\/closures.htm#cs_control_5560287\" target=\"_blank\">Learn more<\/a>.<\/span>","notes":"<li>Bears are active in this area. Do not place your bear canister near a cliff.<\/li>"},"w31a":{"id":"w31a","name":"McGurk Meadow","wpsName":"McGurk  Meadow","region":"ww","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":"<li>You must be four trail miles from Glacier Point before camping. No camping east of the Bridalveil Creek footbridge.<\/li>"},"h29a":{"id":"h29a","name":"Miguel Meadows","wpsName":"Miguel Meadows","region":"hh","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":null},"y02":{"id":"y02","name":"Mirror Lake to Snow Creek","wpsName":"Mirror Lake->Snow Creek","region":"yv","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":"An area closure is in effect to the south and east of the Snow Creek footbridge. <span><a href=\"https:\/\/www.nps.gov\/yose\/learn\/management\/closures.htm#cs_control_5560287\" target=\"_blank\">Learn more<\/a>.<\/span>","notes":"<li>You must camp beyond the top of the switchbacks and outside of the closure area to the south and east of the Snow Creek footbridge.<\/li><li>Bears are active in this area. Do not put your bear canister next to a cliff.<\/li>"},"w34":{"id":"w34","name":"Mono Meadow","wpsName":"Mono Meadow","region":"ww","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":20,"alert":null,"notes":"<li>You may not camp in Little Yosemite Valley your first night with this permit.<\/li><li>Camping is not allowed along the Panorama Trail or at the top of Illilouette Fall.<\/li>"},"t25":{"id":"t25","name":"Mono\/Parker Pass","wpsName":"Mono\/Parker Pass","region":"tm","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":"<li>Travel across Mono Pass before camping.<\/li><li>The Parker Pass Creek watershed is closed to camping, travel over Parker Pass before camping.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t20":{"id":"t20","name":"Murphy Creek","wpsName":"Murphy Creek","region":"tm","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":"<li>Bears have been successful in getting food from backpackers in this area. All food, toiletries, aromatic goods, and garbage must be stored in the canister.<\/li>"},"x02":{"id":"x02","name":"Nelson Lake (cross-country only)","wpsName":"Nelson Lake (cross-country only)","region":"tm","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":"This is a cross-country trailhead, and the trail\/route is not maintained. All members of the party must be proficient at backcountry navigation.","notes":"<li>Cross-country restrictions are in effect. The maximum group size is 8. <span><a href=\"https:\/\/www.nps.gov\/yose\/planyourvisit\/backpackinggroups.htm\" target=\"_blank\">Learn more<\/a>.<\/span><\/li><li>Fires are prohibited at Nelson and Reyman Lakes.<\/li><li>Camping is prohibited at Elizabeth Lake.<\/li><li>Bears are active in this area.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"y08":{"id":"y08","name":"Old Big Oak Flat Road","wpsName":"Old Big Oak Flat Road","region":"yv","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>Camp at least one-half mile back from the rim of the Valley.<\/li><li>The area around the top of the falls is for day use only.<\/li>"},"w33":{"id":"w33","name":"Ostrander Lake","wpsName":"Ostrander (Lost Bear Meadow)","region":"ww","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>All food and toiletries must be stored in proper food storage containers.<\/li><li>Only use existing fire rings.<\/li><li>Pack out all trash, including toilet paper.<\/li>"},"w03c":{"id":"w03c","name":"Pohono Trail (Glacier Point)","wpsName":"Pohono Trail (Glacier Point)","region":"ww","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":"<li>You must be four trail miles from Glacier Point before camping. No camping east of the Bridalveil Creek footbridge.<\/li>"},"w05":{"id":"w05","name":"Pohono Trail (Taft Point)","wpsName":"Pohono Trail (Taft Point)","region":"ww","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>You must be four trail miles from Glacier Point before camping. No camping east of the Bridalveil Creek footbridge including Taft Point.<\/li>"},"y07":{"id":"y07","name":"Pohono Trail (Wawona Tunnel)","wpsName":"Pohono Trail (Wawona Tunnel\/Bridalveil Parking)","region":"yv","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>No camping east of the Bridalveil Creek footbridge. Only use existing fire rings.<\/li>"},"h27":{"id":"h27","name":"Poopenaut Valley","wpsName":"Poopenaut Valley","region":"hh","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>Bears have been successful in getting food from backpackers in the Hetch Hetchy area.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"b15":{"id":"b15","name":"Porcupine Creek","wpsName":"Porcupine Creek","region":"bf","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":20,"alert":null,"notes":"<li>Bears are active in this area. Do not place your bear canister near a cliff.<\/li>"},"t24a":{"id":"t24a","name":"Rafferty Creek to Vogelsang","wpsName":"Rafferty Creek->Vogelsang","region":"tm","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":20,"alert":"There is no longer a toilet at the Vogelsang backpackers campground near Fletcher Lake. If staying at Fletcher Lake please disperse several hunderd feet from the campground boundary when going to the bathroom.","notes":"<li>Fires are prohibited in the Vogesang area, at Boothe Lake, and above 9,600 feet.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"h29c":{"id":"h29c","name":"Rancheria Falls","wpsName":"Rancheria Falls","region":"hh","latitude":null,"longitude":null,"description":null,"quota":21,"capacity":35,"alert":null,"notes":"<li>Swimming and watering of stock directly in streams within one mile of the Reservoir is prohibited. Closure includes Wapama Falls, Rancheria Cascade.<\/li><li>Bears active in Rancheria area.<\/li><li>The Tuolumne River is water source for San Francisco. Please preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"x01":{"id":"x01","name":"Rockslides (cross-country only)","wpsName":"Rockslides (cross-country only)","region":"yv","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":"This is a cross-country trailhead, and the trail\/route is not maintained. All members of the party must be proficient at backcountry navigation.","notes":"<li>Cross-country restrictions are in effect. The maximum group size is 8. <span><a href=\"https:\/\/www.nps.gov\/yose\/planyourvisit\/backpackinggroups.htm\" target=\"_blank\">Learn more<\/a>.<\/span><\/li><li>Camp at least one-half mile back from the rim of the Valley.<\/li><li>The area around the top of the falls is for day use only.<\/li>"},"h28":{"id":"h28","name":"Smith Peak","wpsName":"Smith Peak","region":"hh","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":"<li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"b10":{"id":"b10","name":"South Fork of Tuolumne River","wpsName":"South Fork of Tuolumne River","region":"bf","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t19":{"id":"t19","name":"Sunrise Lakes","wpsName":"Sunrise Lakes","region":"tm","latitude":null,"longitude":null,"description":"Trailhead availability does not reflect availability for <span>Donohue Pass.<\/span> <span>John Muir Trail<\/span> hikers, please <a href=\"?region=jm&th=j19\">click here<\/a>.","quota":9,"capacity":15,"alert":null,"notes":"<li>Bears have successfully obtained food from backpackers in this area on a regular basis. Store all food and toiletries in a bear canister.<\/li><li>Camp at least 100 feet from any water source.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"j19":{"id":"j19","name":"Sunrise Lakes","wpsName":"Sunrise Lakes","region":"jm","latitude":null,"longitude":null,"description":"If you do not plan on exiting the park via Donohue Pass, please <span><a href=\"?region=tm&th=t19\">click here<\/a>.<\/span>","quota":9,"capacity":15,"alert":null,"notes":"<li>Bears have successfully obtained food from backpackers in this area on a regular basis. Store all food and toiletries in a bear canister.<\/li><li>Camp at least 100 feet from any water source.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"y09":{"id":"y09","name":"Tamarack Creek","wpsName":"Tamarack Creek","region":"yv","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>You may not camp at Tamarack Flat Campground with this permit.<\/li><li>Camp at least one-half mile back from the rim of the Valley.<\/li><li>The area around the top of Yosemite Falls is for day use only.<\/li>"},"b14b":{"id":"b14b","name":"Ten Lakes","wpsName":"Ten Lakes","region":"bf","latitude":null,"longitude":null,"description":null,"quota":24,"capacity":40,"alert":null,"notes":"<li>Fires are not allowed above 9600 feet and are permitted only in existing fire rings below that elevation.<\/li><li>Bears are active in this area.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"w31b":{"id":"w31b","name":"Westfall Meadows","wpsName":"Westfall Meadows","region":"ww","latitude":null,"longitude":null,"description":null,"quota":9,"capacity":15,"alert":null,"notes":null},"b12b":{"id":"b12b","name":"White Wolf Campground","wpsName":"White Wolf Campground","region":"bf","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":"The road to White Wolf is closed. You may access the trailhead from Tioga Road.","notes":"<li>You may not camp at White Wolf Campground with this permit.<\/li><li>The Tuolumne River watershed is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li>"},"b12c":{"id":"b12c","name":"White Wolf to Aspen Valley","wpsName":"White Wolf->Aspen Valley","region":"bf","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":"The road to White Wolf is closed. You may access the trailhead from Tioga Road.","notes":"<li>This trail is not used often and portions of the trail are overgrown with vegetation. Bring a good map of the area.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"b12a":{"id":"b12a","name":"White Wolf to Pate Valley","wpsName":"White Wolf->Pate Valley","region":"bf","latitude":null,"longitude":null,"description":null,"quota":18,"capacity":30,"alert":"The road to White Wolf is closed. You may access the trailhead from Tioga Road.","notes":"<li>Bears are active in Pate Valley.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"b12d":{"id":"b12d","name":"White Wolf to Smith Meadow","wpsName":"White Wolf->Smith Meadow (including Harden Lake)","region":"bf","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":"The road to White Wolf is closed. You may access the trailhead from Tioga Road.","notes":"<li>This trail is not used often and portions of the trail are overgrown with vegetation. Bring a good map of the area.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"b14a":{"id":"b14a","name":"Yosemite Creek","wpsName":"Yosemite Creek","region":"bf","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>Camp at least one-half mile back from the rim of the Valley.<\/li><li>The area around the top of Yosemite Falls is for day use only.<\/li>"},"y06":{"id":"y06","name":"Yosemite Falls","wpsName":"Yosemite Falls","region":"yv","latitude":null,"longitude":null,"description":null,"quota":15,"capacity":25,"alert":null,"notes":"<li>Camp at least one-half mile back from the rim of the Valley. <\/li><li>The area around the top of Yosemite Falls and around Yosemite Point is for day use only.<\/li>"},"t23":{"id":"t23","name":"Young Lakes via Dog Lake","wpsName":"Young Lakes via Dog Lake","region":"tm","latitude":null,"longitude":null,"description":null,"quota":12,"capacity":20,"alert":null,"notes":"<li>Fires are not allowed at Young Lakes or anywhere in the park above 9,600 feet.<\/li><li>Bears are active in this area. The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"},"t22c":{"id":"t22c","name":"Young Lakes via Glen Aulin Trail","wpsName":"Young Lakes via Glen Aulin Trail","region":"tm","latitude":null,"longitude":null,"description":null,"quota":6,"capacity":10,"alert":null,"notes":"<li>Fires are not allowed at Young Lakes or anywhere in the park above 9,600 feet.<\/li><li>Bears are active in this area.<\/li><li>The Tuolumne River is a water source for San Francisco. Follow Leave No Trace camping techniques to preserve water quality.<\/li><li>Along the Tuolumne Watershed, ensure all washing and waste is 300 feet from water.<\/li>"
             }}}
         }"#;
-        let res = serde_json::from_str::<Response<Trailheads>>(test);
+
+    #[test]
+    fn parse_trailheads() {
+        let res = serde_json::from_str::<Response<Trailheads>>(TRAILHEADS);
         let resp = res.expect("derp");
         println!("{:?}", resp)
     }
@@ -0,0 +1,150 @@
+use crate::matrix::Matrix;
+use crate::table::{FEW, PLENTY};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    /// Raw occupancy per trailhead id and date as comma separated lines
+    Csv,
+    /// A standalone HTML page with a colored table per region
+    Html,
+    /// A Markdown table per region
+    Md,
+}
+
+const STYLE: &str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+td { text-align: right; }
+td.date { text-align: left; white-space: nowrap; }
+td.plenty { background: #c6efce; }
+td.some { background: #ffeb9c; }
+td.few { background: #ffc7ce; }";
+
+/// A page with a table per region, dates as rows and trailheads as columns, shaded like the
+/// terminal table. Styles are inline so the page can be sent as a single file.
+pub fn html(matrices: &BTreeMap<&str, Matrix>, generated: NaiveDate) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Yosemite wilderness permit availability</title>\n");
+    out.push_str(&format!("<style>\n{}\n</style>\n</head>\n<body>\n", STYLE));
+    out.push_str("<h1>Yosemite wilderness permit availability</h1>\n");
+    out.push_str(&format!("<p>Open spots as of {}.</p>\n", generated));
+
+    for (region, matrix) in matrices {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<tr><th>Date</th>",
+            escape(region)
+        ));
+        for trailhead in &matrix.trailheads {
+            out.push_str(&format!("<th>{}</th>", escape(&trailhead.name)));
+        }
+        out.push_str("</tr>\n");
+
+        for (date, values) in &matrix.dates {
+            out.push_str(&format!("<tr><td class=\"date\">{}</td>", date));
+            for value in values {
+                match value {
+                    Some(open) => {
+                        let class = shade(u32::from(*open));
+                        out.push_str(&format!("<td class=\"{}\">{}</td>", class, open))
+                    }
+                    None => out.push_str("<td></td>"),
+                }
+            }
+            out.push_str("</tr>\n");
+        }
+
+        out.push_str("</table>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// A heading and table per region, dates as rows and trailheads as columns.
+pub fn markdown(matrices: &BTreeMap<&str, Matrix>) -> String {
+    let mut out = String::new();
+
+    for (region, matrix) in matrices {
+        out.push_str(&format!("## {}\n\n| Date |", region));
+        for trailhead in &matrix.trailheads {
+            out.push_str(&format!(" {} |", trailhead.name.replace('|', "\\|")));
+        }
+        out.push_str("\n| --- |");
+        out.push_str(&" ---: |".repeat(matrix.trailheads.len()));
+        out.push('\n');
+
+        for (date, values) in &matrix.dates {
+            out.push_str(&format!("| {} |", date));
+            for value in values {
+                match value {
+                    Some(open) => out.push_str(&format!(" {} |", open)),
+                    None => out.push_str("  |"),
+                }
+            }
+            out.push('\n');
+        }
+
+        out.push('\n');
+    }
+
+    out
+}
+
+fn shade(open: u32) -> &'static str {
+    if open >= PLENTY {
+        "plenty"
+    } else if open >= FEW {
+        "some"
+    } else {
+        "few"
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::markup::{html, markdown};
+    use crate::matrix::by_region;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn render_matrices() {
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+        let rows = vec![
+            AvailabilityRow::new(first, &cathedral, 7),
+            AvailabilityRow::new(first, &lyell, 2),
+            AvailabilityRow::new(second, &lyell, 4),
+        ];
+        let matrices = by_region(rows);
+
+        assert_eq!(
+            markdown(&matrices),
+            "## Tuolumne Meadows\n\n\
+             | Date | Cathedral Lakes | Lyell Canyon |\n\
+             | --- | ---: | ---: |\n\
+             | 2020-09-10 | 7 | 2 |\n\
+             | 2020-09-11 |  | 4 |\n\n"
+        );
+
+        let page = html(&matrices, first);
+        assert!(page.contains("<h2>Tuolumne Meadows</h2>"));
+        assert!(page.contains(
+            "<tr><td class=\"date\">2020-09-11</td><td></td><td class=\"some\">4</td></tr>"
+        ));
+    }
+}
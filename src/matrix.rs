@@ -0,0 +1,89 @@
+use crate::availability::AvailabilityRow;
+use crate::Trailhead;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Availability laid out with dates as rows and trailheads as columns. Cells for trailheads
+/// with nothing open on a date are `None`.
+#[derive(Debug, Default)]
+pub struct Matrix<'a> {
+    pub trailheads: Vec<&'a Trailhead>,
+    pub dates: BTreeMap<NaiveDate, Vec<Option<u8>>>,
+}
+
+/// One matrix per region display name, with trailheads ordered by name.
+pub fn by_region<'a>(
+    rows: impl IntoIterator<Item = AvailabilityRow<'a>>,
+) -> BTreeMap<&'a str, Matrix<'a>> {
+    let mut cells = BTreeMap::<&str, BTreeMap<NaiveDate, BTreeMap<(&str, &str), u8>>>::new();
+    let mut trailheads = BTreeMap::<&str, BTreeMap<(&str, &str), &Trailhead>>::new();
+
+    for row in rows {
+        let trailhead = row.trailhead;
+        let region = trailhead.region_name().unwrap_or("Other");
+        let key = (trailhead.name.as_str(), trailhead.id.as_str());
+        trailheads.entry(region).or_default().insert(key, trailhead);
+        cells
+            .entry(region)
+            .or_default()
+            .entry(row.date)
+            .or_default()
+            .insert(key, row.availability);
+    }
+
+    trailheads
+        .into_iter()
+        .map(|(region, trailheads)| {
+            let dates = cells
+                .remove(region)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(date, values)| {
+                    let row = trailheads.keys().map(|key| values.get(key).copied());
+                    (date, row.collect())
+                })
+                .collect();
+
+            let matrix = Matrix {
+                trailheads: trailheads.into_values().collect(),
+                dates,
+            };
+            (region, matrix)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::matrix::by_region;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn group_into_region_matrices() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let snow_creek = Trailhead::test("y02", "Snow Creek", "yv");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+
+        let matrices = by_region(vec![
+            AvailabilityRow::new(first, &lyell, 3),
+            AvailabilityRow::new(second, &cathedral, 1),
+            AvailabilityRow::new(second, &lyell, 2),
+            AvailabilityRow::new(first, &snow_creek, 4),
+        ]);
+
+        let tuolumne = &matrices["Tuolumne Meadows"];
+        let names = tuolumne.trailheads.iter().map(|t| t.name.as_str());
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            vec!["Cathedral Lakes", "Lyell Canyon"]
+        );
+        assert_eq!(tuolumne.dates[&first], vec![None, Some(3)]);
+        assert_eq!(tuolumne.dates[&second], vec![Some(1), Some(2)]);
+        assert_eq!(matrices["Yosemite Valley"].dates[&first], vec![Some(4)]);
+        assert_eq!(matrices.len(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::fees::PermitSource;
+use crate::output::SCHEMA_VERSION;
+use crate::{ReportDate, Trailhead};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Where trailheads and reports come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// yosemite.org, which needs a session cookie
+    Upstream,
+    /// A JSON document published with `--format json` by another instance
+    Mirror(String),
+    /// The latest snapshot recorded with `--store`
+    Store(PathBuf),
+}
+
+impl Source {
+    /// Parses `upstream`, `mirror=<url>` or `store=<path>`.
+    pub fn parse(s: &str) -> Result<Source, String> {
+        match s.split_once('=') {
+            None if s == "upstream" => Ok(Source::Upstream),
+            Some(("mirror", url)) if !url.is_empty() => Ok(Source::Mirror(url.to_string())),
+            Some(("store", path)) if !path.is_empty() => Ok(Source::Store(PathBuf::from(path))),
+            _ => Err(format!(
+                "unknown source {:?}, expected upstream, mirror=<url> or store=<path>",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MirrorTrailhead {
+    name: String,
+    region: Option<String>,
+    quota: u8,
+    capacity: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct Document {
+    // documents published before the version was added have the first layout
+    #[serde(default = "first_version")]
+    schema_version: u32,
+    trailheads: BTreeMap<String, MirrorTrailhead>,
+    availability: BTreeMap<NaiveDate, BTreeMap<String, i16>>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+/// Availability read back from another instance. Only trailheads with open spots are
+/// published, so every other trailhead is treated as full.
+#[derive(Debug)]
+pub struct Mirror {
+    pub url: String,
+    pub trailheads: BTreeMap<String, Trailhead>,
+    reports: Vec<ReportDate>,
+}
+
+impl Mirror {
+    pub async fn fetch(url: &str, today: NaiveDate) -> Result<Mirror, Box<dyn Error>> {
+        let document = reqwest::get(url)
+            .await?
+            .error_for_status()?
+            .json::<Document>()
+            .await?;
+
+        if document.schema_version > SCHEMA_VERSION {
+            return Err(format!(
+                "{} is published with schema version {}, this version reads up to {}",
+                url, document.schema_version, SCHEMA_VERSION
+            )
+            .into());
+        }
+
+        Ok(Mirror::from_document(url, document, today))
+    }
+
+    /// Turns open spots back into occupancy against the capacity that applies on each date.
+    fn from_document(url: &str, document: Document, today: NaiveDate) -> Mirror {
+        let trailheads = document
+            .trailheads
+            .into_iter()
+            .map(|(id, trailhead)| {
+                let trailhead = Trailhead {
+                    id: id.clone(),
+                    name: trailhead.name,
+                    region: trailhead.region,
+                    quota: trailhead.quota,
+                    capacity: trailhead.capacity,
+                    description: None,
+                    alert: None,
+                    notes: None,
+                    latitude: None,
+                    longitude: None,
+                };
+                (id, trailhead)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let reports = document
+            .availability
+            .into_iter()
+            .map(|(date, open)| {
+                let values = open
+                    .into_iter()
+                    .filter_map(|(id, remaining)| {
+                        let trailhead = trailheads.get(&id)?;
+                        let capacity = PermitSource::for_date(date, today).capacity(trailhead);
+                        let occupancy = i16::from(capacity) - remaining;
+                        Some((id, u8::try_from(occupancy.max(0)).unwrap_or(u8::MAX)))
+                    })
+                    .collect();
+                ReportDate { date, values }
+            })
+            .collect();
+
+        Mirror {
+            url: url.to_string(),
+            trailheads,
+            reports,
+        }
+    }
+
+    /// The published dates, limited to the trailheads in `region`.
+    pub fn report(&self, region: &str) -> Vec<ReportDate> {
+        self.reports
+            .iter()
+            .map(|report| report.in_region(&self.trailheads, region))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, AvailabilitySet, Overbooking};
+    use crate::mirror::{Mirror, Source};
+    use crate::output::JsonAvailability;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parse_sources() {
+        assert_eq!(Source::parse("upstream"), Ok(Source::Upstream));
+        assert_eq!(
+            Source::parse("mirror=https://example.com/yose.json"),
+            Ok(Source::Mirror("https://example.com/yose.json".to_string()))
+        );
+        assert_eq!(
+            Source::parse("store=yose.db"),
+            Ok(Source::Store(PathBuf::from("yose.db")))
+        );
+        assert!(Source::parse("mirror=").is_err());
+        assert!(Source::parse("store=").is_err());
+        assert!(Source::parse("recreation.gov").is_err());
+    }
+
+    #[test]
+    fn round_trip_published_json() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let today = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let walk_up = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+        let reservation = NaiveDate::from_ymd_opt(2020, 10, 1).unwrap();
+        let rows = vec![
+            AvailabilityRow::from_occupancy(walk_up, &lyell, 7, 10),
+            AvailabilityRow::from_occupancy(reservation, &lyell, 4, 6),
+        ];
+        let published =
+            serde_json::to_string(&JsonAvailability::new(&rows, Overbooking::Negative)).unwrap();
+
+        let document = serde_json::from_str(&published).unwrap();
+        let mirror = Mirror::from_document("https://example.com", document, today);
+        assert!(mirror.report("yv").iter().all(|r| r.values.is_empty()));
+
+        let rows = AvailabilitySet::from_reports(
+            mirror.report("tm"),
+            &mirror.trailheads,
+            today,
+            Overbooking::Clamp,
+            false,
+        );
+        let rows = rows
+            .rows()
+            .iter()
+            .map(|row| (row.date, row.trailhead.id.as_str(), row.availability))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![(walk_up, "t24b", 3), (reservation, "t24b", 2)]);
+    }
+}
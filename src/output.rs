@@ -0,0 +1,115 @@
+use crate::availability::{AvailabilityRow, Overbooking};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    /// Colored tables per region, the default on a terminal
+    Table,
+    /// Comma separated lines, the default otherwise
+    Csv,
+    /// A single JSON document
+    Json,
+    /// One JSON object per row, printed region by region as the reports arrive
+    Jsonl,
+    /// An iCalendar file with an all day event per date that has open spots
+    Ics,
+    /// InfluxDB line protocol with a point per trailhead and date, for Telegraf or --influx-url
+    Influx,
+}
+
+/// Version of the JSON documents, JSON records and export layouts. Bumped whenever a field is
+/// renamed, removed or changes meaning, adding fields keeps the version. CSV, tables, calendars
+/// and influx lines carry no version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct TrailheadInfo<'a> {
+    pub name: &'a str,
+    pub region: Option<&'a str>,
+    pub region_name: Option<&'a str>,
+    pub quota: u8,
+    pub capacity: u8,
+}
+
+/// Open spots per date and trailhead id, negative when overbooked unless clamped, with the
+/// details of every trailhead mentioned.
+#[derive(Debug, Serialize)]
+pub struct JsonAvailability<'a> {
+    pub schema_version: u32,
+    pub trailheads: BTreeMap<&'a str, TrailheadInfo<'a>>,
+    pub availability: BTreeMap<NaiveDate, BTreeMap<&'a str, i16>>,
+}
+
+impl<'a> JsonAvailability<'a> {
+    pub fn new(rows: &[AvailabilityRow<'a>], overbooking: Overbooking) -> Self {
+        let mut json = JsonAvailability {
+            schema_version: SCHEMA_VERSION,
+            trailheads: BTreeMap::new(),
+            availability: BTreeMap::new(),
+        };
+
+        for row in rows {
+            let trailhead = row.trailhead;
+            json.trailheads
+                .entry(trailhead.id.as_str())
+                .or_insert_with(|| TrailheadInfo {
+                    name: trailhead.name.as_str(),
+                    region: trailhead.region.as_deref(),
+                    region_name: trailhead.region_name(),
+                    quota: trailhead.quota,
+                    capacity: trailhead.capacity,
+                });
+            json.availability
+                .entry(row.date)
+                .or_default()
+                .insert(trailhead.id.as_str(), row.availability(overbooking));
+        }
+
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, Overbooking};
+    use crate::output::JsonAvailability;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    #[test]
+    fn availability_as_json() {
+        let lyell = Trailhead {
+            quota: 21,
+            capacity: 35,
+            ..Trailhead::test("t24b", "Lyell Canyon", "tm")
+        };
+        let rows = vec![
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 10).unwrap(), &lyell, 2),
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 11).unwrap(), &lyell, 5),
+        ];
+
+        assert_eq!(
+            serde_json::to_value(JsonAvailability::new(&rows, Overbooking::Clamp)).unwrap(),
+            json!({
+                "schema_version": 1,
+                "trailheads": {
+                    "t24b": {
+                        "name": "Lyell Canyon",
+                        "region": "tm",
+                        "region_name": "Tuolumne Meadows",
+                        "quota": 21,
+                        "capacity": 35,
+                    },
+                },
+                "availability": {
+                    "2020-09-10": {"t24b": 2},
+                    "2020-09-11": {"t24b": 5},
+                },
+            })
+        );
+    }
+}
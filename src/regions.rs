@@ -0,0 +1,13 @@
+/// Display name for an upstream region code, falling back to the code itself for regions that
+/// are not known yet.
+pub fn name(code: &str) -> &str {
+    match code {
+        "bf" => "Big Oak Flat & Tioga Road",
+        "hh" => "Hetch Hetchy",
+        "jm" => "John Muir Trail",
+        "tm" => "Tuolumne Meadows",
+        "ww" => "Glacier Point & Wawona",
+        "yv" => "Yosemite Valley",
+        other => other,
+    }
+}
@@ -0,0 +1,126 @@
+use crate::availability::{AvailabilitySet, Overbooking};
+use crate::columns::{self, Column};
+use crate::filter::Filter;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+commands:
+  show                  print the current rows
+  filter <expression>   keep rows matching a --filter expression
+  sort <column> [desc]  sort by date, id, name, region, availability, quota or capacity
+  reset                 undo every filter and sort
+  quit                  exit";
+
+/// Explores rows already fetched without fetching them again, reading one command per line.
+pub fn run(
+    all: &AvailabilitySet,
+    region_names: bool,
+    overbooking: Overbooking,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut rows = all.rows().to_vec();
+    writeln!(output, "{} rows, type help for commands", rows.len())?;
+
+    for line in input.lines() {
+        let line = line?;
+        let (command, argument) = match line.trim().split_once(' ') {
+            Some((command, argument)) => (command, argument.trim()),
+            None => (line.trim(), ""),
+        };
+
+        match command {
+            "" => continue,
+            "show" => {
+                let mut writer = csv::Writer::from_writer(&mut output);
+                for row in &rows {
+                    let name = crate::display_name(row.trailhead, region_names);
+                    writer.serialize((row.date, name, row.availability(overbooking)))?;
+                }
+                writer.flush()?;
+            }
+            "filter" => match Filter::parse(argument) {
+                Ok(filter) => {
+                    rows.retain(|row| filter.matches(row, overbooking));
+                    writeln!(output, "{} rows", rows.len())?;
+                }
+                Err(err) => writeln!(output, "invalid filter: {}", err)?,
+            },
+            "sort" => {
+                let (column, desc) = match argument.split_once(' ') {
+                    Some((column, "desc")) => (column, true),
+                    _ => (argument, false),
+                };
+                match Column::from_name(column).filter(|&column| column != Column::Cost) {
+                    Some(column) => columns::sort_ordered(&mut rows, desc, |rows| {
+                        column.sort(rows, overbooking)
+                    }),
+                    None => writeln!(output, "can't sort by {:?}", column)?,
+                }
+            }
+            "reset" => {
+                rows = all.rows().to_vec();
+                writeln!(output, "{} rows", rows.len())?;
+            }
+            "quit" | "exit" => break,
+            "help" => writeln!(output, "{}", HELP)?,
+            _ => writeln!(
+                output,
+                "unknown command {:?}, type help for commands",
+                command
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::{AvailabilityRow, AvailabilitySet, Overbooking};
+    use crate::repl::run;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn filter_and_sort() {
+        let lyell = Trailhead {
+            quota: 21,
+            capacity: 35,
+            ..Trailhead::test("t24b", "Lyell Canyon", "tm")
+        };
+        let white_wolf = Trailhead::test("b12a", "Tioga Road, White Wolf", "bf");
+        let first = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let second = NaiveDate::from_ymd_opt(2020, 9, 11).unwrap();
+        let third = NaiveDate::from_ymd_opt(2020, 9, 12).unwrap();
+        let rows = vec![
+            AvailabilityRow::new(first, &lyell, 2),
+            AvailabilityRow::new(second, &lyell, 5),
+            AvailabilityRow::new(third, &lyell, 1),
+            AvailabilityRow::new(third, &white_wolf, 5),
+        ]
+        .into_iter()
+        .collect::<AvailabilitySet>();
+
+        let input = "filter availability > 1\nsort availability desc\nshow\nreset\nquit\nshow\n";
+        let mut output = Vec::new();
+        run(
+            &rows,
+            false,
+            Overbooking::Clamp,
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "4 rows, type help for commands\n\
+             3 rows\n\
+             2020-09-11,Lyell Canyon,5\n\
+             2020-09-12,\"Tioga Road, White Wolf\",5\n\
+             2020-09-10,Lyell Canyon,2\n\
+             4 rows\n"
+        );
+    }
+}
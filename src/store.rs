@@ -0,0 +1,282 @@
+use crate::availability::{AvailabilitySet, Overbooking};
+use crate::{ReportDate, Trailhead};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::US::Pacific;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    fetched TEXT NOT NULL,
+    source TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS trailheads (
+    snapshot INTEGER NOT NULL REFERENCES snapshots (id),
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    region TEXT,
+    quota INTEGER NOT NULL,
+    capacity INTEGER NOT NULL,
+    description TEXT,
+    alert TEXT,
+    notes TEXT,
+    latitude REAL,
+    longitude REAL,
+    PRIMARY KEY (snapshot, id)
+);
+CREATE TABLE IF NOT EXISTS occupancy (
+    snapshot INTEGER NOT NULL REFERENCES snapshots (id),
+    date TEXT NOT NULL,
+    trailhead TEXT NOT NULL,
+    occupancy INTEGER NOT NULL,
+    PRIMARY KEY (snapshot, date, trailhead)
+);
+";
+
+/// Every fetched trailhead list and report, kept in a SQLite database so availability can be
+/// looked at over time.
+pub struct Store {
+    connection: Connection,
+}
+
+/// The trailheads and reports fetched in one run. Only the regions the run needed are in it.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub fetched: DateTime<Utc>,
+    pub source: String,
+    pub trailheads: BTreeMap<String, Trailhead>,
+    reports: Vec<ReportDate>,
+}
+
+impl Store {
+    /// Opens the database at `path`, creating it and its tables when missing.
+    pub fn open(path: &Path) -> rusqlite::Result<Store> {
+        Store::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> rusqlite::Result<Store> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Store { connection })
+    }
+
+    /// Records a snapshot and returns its id.
+    pub fn record(
+        &mut self,
+        fetched: DateTime<Utc>,
+        source: &str,
+        trailheads: &BTreeMap<String, Trailhead>,
+        reports: &[ReportDate],
+    ) -> rusqlite::Result<i64> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO snapshots (fetched, source) VALUES (?1, ?2)",
+            params![fetched, source],
+        )?;
+        let snapshot = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO trailheads (snapshot, id, name, region, quota, capacity, \
+                 description, alert, notes, latitude, longitude) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for trailhead in trailheads.values() {
+                insert.execute(params![
+                    snapshot,
+                    trailhead.id,
+                    trailhead.name,
+                    trailhead.region,
+                    trailhead.quota,
+                    trailhead.capacity,
+                    trailhead.description,
+                    trailhead.alert,
+                    trailhead.notes,
+                    trailhead.latitude,
+                    trailhead.longitude,
+                ])?;
+            }
+
+            let mut insert = transaction.prepare(
+                "INSERT OR REPLACE INTO occupancy (snapshot, date, trailhead, occupancy) \
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for report in reports {
+                for (id, occupancy) in &report.values {
+                    insert.execute(params![snapshot, report.date, id, occupancy])?;
+                }
+            }
+        }
+
+        transaction.commit()?;
+        Ok(snapshot)
+    }
+
+    /// Snapshot ids from the first recorded to the latest.
+    pub fn ids(&self) -> rusqlite::Result<Vec<i64>> {
+        let mut select = self
+            .connection
+            .prepare("SELECT id FROM snapshots ORDER BY fetched, id")?;
+        let ids = select.query_map([], |row| row.get(0))?.collect();
+        ids
+    }
+
+    /// Every snapshot from the first recorded to the latest.
+    pub fn snapshots(&self) -> rusqlite::Result<Vec<Snapshot>> {
+        self.ids()?.into_iter().map(|id| self.load(id)).collect()
+    }
+
+    /// The most recently recorded snapshot, if any.
+    pub fn latest(&self) -> rusqlite::Result<Option<Snapshot>> {
+        let id = self
+            .connection
+            .query_row(
+                "SELECT id FROM snapshots ORDER BY fetched DESC, id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match id {
+            Some(id) => self.load(id).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load(&self, id: i64) -> rusqlite::Result<Snapshot> {
+        let (fetched, source) = self.connection.query_row(
+            "SELECT fetched, source FROM snapshots WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut select = self.connection.prepare(
+            "SELECT id, name, region, quota, capacity, description, alert, notes, latitude, \
+             longitude FROM trailheads WHERE snapshot = ?1",
+        )?;
+        let trailheads = select
+            .query_map([id], |row| {
+                Ok(Trailhead {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    region: row.get(2)?,
+                    quota: row.get(3)?,
+                    capacity: row.get(4)?,
+                    description: row.get(5)?,
+                    alert: row.get(6)?,
+                    notes: row.get(7)?,
+                    latitude: row.get(8)?,
+                    longitude: row.get(9)?,
+                })
+            })?
+            .map(|trailhead| trailhead.map(|trailhead| (trailhead.id.clone(), trailhead)))
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut select = self.connection.prepare(
+            "SELECT date, trailhead, occupancy FROM occupancy WHERE snapshot = ?1 ORDER BY date",
+        )?;
+        let mut dates = BTreeMap::<NaiveDate, BTreeMap<String, u8>>::new();
+        for value in select.query_map([id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))? {
+            let (date, trailhead, occupancy) = value?;
+            dates.entry(date).or_default().insert(trailhead, occupancy);
+        }
+        let reports = dates
+            .into_iter()
+            .map(|(date, values)| ReportDate { date, values })
+            .collect();
+
+        Ok(Snapshot {
+            fetched,
+            source,
+            trailheads,
+            reports,
+        })
+    }
+}
+
+impl Snapshot {
+    /// Every recorded row, full trailheads included, with the limits that applied on the day
+    /// the snapshot was fetched.
+    pub fn rows(&self) -> AvailabilitySet<'_> {
+        let today = self.fetched.with_timezone(&Pacific).date_naive();
+        AvailabilitySet::from_reports(
+            self.reports.iter().cloned(),
+            &self.trailheads,
+            today,
+            Overbooking::Clamp,
+            true,
+        )
+    }
+
+    /// The recorded dates, limited to the trailheads in `region`.
+    pub fn report(&self, region: &str) -> Vec<ReportDate> {
+        self.reports
+            .iter()
+            .map(|report| report.in_region(&self.trailheads, region))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::Store;
+    use crate::{ReportDate, Trailhead};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use rusqlite::Connection;
+    use std::collections::BTreeMap;
+
+    fn trailhead(id: &str, name: &str, region: &str) -> Trailhead {
+        Trailhead {
+            latitude: Some(37.8736),
+            longitude: Some(-119.3425),
+            ..Trailhead::test(id, name, region)
+        }
+    }
+
+    #[test]
+    fn record_and_load_snapshots() {
+        let mut store = Store::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        assert!(store.latest().unwrap().is_none());
+
+        let trailheads = vec![
+            trailhead("t24b", "Lyell Canyon", "tm"),
+            trailhead("t10", "Happy Isles", "yv"),
+        ]
+        .into_iter()
+        .map(|trailhead| (trailhead.id.clone(), trailhead))
+        .collect::<BTreeMap<_, _>>();
+        let date = NaiveDate::from_ymd_opt(2020, 9, 10).unwrap();
+        let report = |occupancy| ReportDate {
+            date,
+            values: vec![("t24b".to_string(), occupancy), ("t10".to_string(), 2)]
+                .into_iter()
+                .collect(),
+        };
+
+        let first = Utc.with_ymd_and_hms(2020, 9, 1, 12, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2020, 9, 2, 12, 0, 0).unwrap();
+        let source = "https://yosemite.org";
+        store
+            .record(first, source, &trailheads, &[report(4)])
+            .unwrap();
+        let id = store
+            .record(second, source, &trailheads, &[report(7)])
+            .unwrap();
+
+        let snapshot = store.latest().unwrap().unwrap();
+        assert_eq!(snapshot.fetched, second);
+        assert_eq!(snapshot.source, source);
+        assert_eq!(snapshot.trailheads["t24b"].name, "Lyell Canyon");
+        assert_eq!(snapshot.trailheads["t24b"].latitude, Some(37.8736));
+
+        let report = snapshot.report("tm");
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].date, date);
+        assert_eq!(report[0].values.get("t24b"), Some(&7));
+        assert_eq!(report[0].values.get("t10"), None);
+
+        let earlier = store.load(id - 1).unwrap();
+        assert_eq!(earlier.report("tm")[0].values.get("t24b"), Some(&4));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::availability::AvailabilityRow;
+use chrono::{Datelike, Duration, NaiveDate};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Summary {
+    /// Open spots per region per date
+    Region,
+    /// Open spots per region per week, starting on Monday
+    Week,
+}
+
+/// Open spots added up over a region's trailheads, and the most open at any one of them.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Total {
+    pub sum: u32,
+    pub max: u8,
+}
+
+/// Totals open spots by (date or week start, region name).
+pub fn summarize<'a>(
+    summary: Summary,
+    rows: impl IntoIterator<Item = AvailabilityRow<'a>>,
+) -> BTreeMap<(NaiveDate, &'a str), Total> {
+    let mut totals = BTreeMap::<_, Total>::new();
+
+    for row in rows {
+        let date = match summary {
+            Summary::Region => row.date,
+            Summary::Week => week_start(row.date),
+        };
+
+        let region = row.trailhead.region_name().unwrap_or("Other");
+        let total = totals.entry((date, region)).or_default();
+        total.sum += u32::from(row.availability);
+        total.max = total.max.max(row.availability);
+    }
+
+    totals
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::summary::{summarize, Summary, Total};
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn totals_per_region_and_week() {
+        let cathedral = Trailhead::test("t21", "t21", "tm");
+        let lyell = Trailhead::test("t24b", "t24b", "tm");
+        let snow_creek = Trailhead::test("y02", "y02", "yv");
+
+        // 2020-09-13 is a Sunday, the 14th starts the next week
+        let sunday = NaiveDate::from_ymd_opt(2020, 9, 13).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2020, 9, 14).unwrap();
+        let rows = vec![
+            AvailabilityRow::new(sunday, &cathedral, 2),
+            AvailabilityRow::new(sunday, &lyell, 3),
+            AvailabilityRow::new(monday, &lyell, 4),
+            AvailabilityRow::new(monday, &snow_creek, 1),
+        ];
+
+        let by_date = summarize(Summary::Region, rows.clone());
+        let total = |sum, max| Total { sum, max };
+        assert_eq!(by_date[&(sunday, "Tuolumne Meadows")], total(5, 3));
+        assert_eq!(by_date[&(monday, "Tuolumne Meadows")], total(4, 4));
+        assert_eq!(by_date[&(monday, "Yosemite Valley")], total(1, 1));
+
+        let by_week = summarize(Summary::Week, rows);
+        assert_eq!(
+            by_week[&(
+                NaiveDate::from_ymd_opt(2020, 9, 7).unwrap(),
+                "Tuolumne Meadows"
+            )],
+            total(5, 3)
+        );
+        assert_eq!(by_week[&(monday, "Tuolumne Meadows")], total(4, 4));
+        assert_eq!(by_week.len(), 3);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::matrix::Matrix;
+use crate::summary::Total;
+use chrono::NaiveDate;
+use comfy_table::{Cell, CellAlignment, Color, Table};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Open counts at or above `PLENTY` are green, below `FEW` red, and yellow in between.
+pub const PLENTY: u32 = 6;
+pub const FEW: u32 = 3;
+
+/// A table per region with dates as rows and trailheads as columns. Colors are only added when
+/// `styled` is set, so the text can be copied or saved as is.
+pub fn availability(matrices: &BTreeMap<&str, Matrix>, styled: bool) -> String {
+    let mut out = String::new();
+
+    for (region, matrix) in matrices {
+        let mut table = table(styled);
+        let mut header = vec![Cell::new("Date")];
+        header.extend(matrix.trailheads.iter().map(|t| Cell::new(&t.name)));
+        table.set_header(header);
+
+        for (date, values) in &matrix.dates {
+            let mut row = vec![Cell::new(date)];
+            row.extend(values.iter().map(|value| match value {
+                Some(availability) => count(u32::from(*availability)),
+                None => Cell::new(""),
+            }));
+            table.add_row(row);
+        }
+
+        out.push_str(&format!("{}\n{}\n\n", region, table));
+    }
+
+    out
+}
+
+/// Total open spots with dates as rows and regions as columns, with the most open at any one
+/// trailhead next to them. Cells are colored by that most.
+pub fn summary(totals: &BTreeMap<(NaiveDate, &str), Total>, styled: bool) -> String {
+    let regions = totals
+        .keys()
+        .map(|&(_, region)| region)
+        .collect::<BTreeSet<_>>();
+    let dates = totals
+        .keys()
+        .map(|&(date, _)| date)
+        .collect::<BTreeSet<_>>();
+
+    let mut table = table(styled);
+    let mut header = vec![Cell::new("Date")];
+    header.extend(regions.iter().map(Cell::new));
+    table.set_header(header);
+
+    for date in dates {
+        let mut row = vec![Cell::new(date)];
+        row.extend(
+            regions
+                .iter()
+                .map(|&region| match totals.get(&(date, region)) {
+                    Some(total) => {
+                        let text = format!("{} (max {})", total.sum, total.max);
+                        Cell::new(text)
+                            .fg(shade(u32::from(total.max)))
+                            .set_alignment(CellAlignment::Right)
+                    }
+                    None => Cell::new(""),
+                }),
+        );
+        table.add_row(row);
+    }
+
+    format!("{}\n", table)
+}
+
+fn table(styled: bool) -> Table {
+    let mut table = Table::new();
+    if styled {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+    table
+}
+
+fn count(open: u32) -> Cell {
+    Cell::new(open)
+        .fg(shade(open))
+        .set_alignment(CellAlignment::Right)
+}
+
+fn shade(open: u32) -> Color {
+    if open >= PLENTY {
+        Color::Green
+    } else if open >= FEW {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::matrix::by_region;
+    use crate::table::availability;
+    use crate::Trailhead;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn color_only_when_styled() {
+        let lyell = Trailhead {
+            quota: 21,
+            capacity: 35,
+            ..Trailhead::test("t24b", "Lyell Canyon", "tm")
+        };
+        let rows = vec![
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 10).unwrap(), &lyell, 2),
+            AvailabilityRow::new(NaiveDate::from_ymd_opt(2020, 9, 11).unwrap(), &lyell, 8),
+        ];
+        let matrices = by_region(rows);
+
+        let plain = availability(&matrices, false);
+        assert!(plain.starts_with("Tuolumne Meadows\n"));
+        assert!(plain.contains("Lyell Canyon"));
+        assert!(plain.contains("2020-09-11"));
+        assert!(!plain.contains('\u{1b}'));
+
+        // red for 2 and green for 8
+        let styled = availability(&matrices, true);
+        assert!(styled.contains("\u{1b}[38;5;9m"));
+        assert!(styled.contains("\u{1b}[38;5;10m"));
+    }
+}
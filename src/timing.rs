@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+/// Wall clock time spent in each step of a run. Region reports are fetched concurrently, so
+/// their times overlap and add up to more than the time spent fetching all of them.
+#[derive(Debug, Default)]
+pub struct Timings {
+    steps: Vec<(String, Duration)>,
+}
+
+impl Timings {
+    /// Records the time since `start` and returns the current time to start the next step.
+    pub fn record(&mut self, step: impl Into<String>, start: Instant) -> Instant {
+        let now = Instant::now();
+        self.steps.push((step.into(), now - start));
+        now
+    }
+
+    pub fn add(&mut self, step: impl Into<String>, duration: Duration) {
+        self.steps.push((step.into(), duration));
+    }
+
+    #[cfg(test)]
+    pub fn steps(&self) -> impl Iterator<Item = &str> {
+        self.steps.iter().map(|(step, _)| step.as_str())
+    }
+
+    /// One line per step with the time in milliseconds.
+    pub fn report(&self) -> String {
+        let width = self
+            .steps
+            .iter()
+            .map(|(step, _)| step.len())
+            .max()
+            .unwrap_or_default();
+
+        self.steps
+            .iter()
+            .map(|(step, duration)| {
+                let millis = duration.as_secs_f64() * 1000.0;
+                format!("{:<width$} {:>9.1} ms\n", step, millis, width = width)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timing::Timings;
+    use std::time::Duration;
+
+    #[test]
+    fn report_steps() {
+        let mut timings = Timings::default();
+        timings.add("trailheads", Duration::from_millis(250));
+        timings.add("report tm", Duration::from_micros(1500));
+
+        assert_eq!(
+            timings.report(),
+            "trailheads     250.0 ms\nreport tm        1.5 ms\n"
+        );
+    }
+}
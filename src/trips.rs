@@ -0,0 +1,388 @@
+use crate::availability::AvailabilityRow;
+use crate::fuzzy;
+use crate::Selection;
+use crate::{ReportDate, Trailhead};
+use chrono::{Duration, NaiveDate};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Find start dates for a trip of some number of nights
+#[derive(Debug, Args)]
+pub struct Trips {
+    /// Nights out, the trip ends this many days after it starts
+    #[arg(long, env = "YOSE_NIGHTS")]
+    pub nights: u32,
+
+    /// Also require one of these trailheads to be open on the last day, for loops that come
+    /// out elsewhere. Names are matched loosely, repeat for more than one
+    #[arg(long, env = "YOSE_EXIT")]
+    pub exit: Vec<String>,
+
+    /// Only John Muir Trail entries with spots left on their Donohue Pass exit quota on the
+    /// start date, the day the quota is assumed to be counted on
+    #[arg(long, env = "YOSE_DONOHUE")]
+    pub donohue: bool,
+
+    /// Also list open entries that fail an exit or Donohue Pass quota, naming each quota that
+    /// fails in a blocked_by column. A Donohue Pass quota is named by the exit the entry is
+    /// assumed to draw on, d02 for Lyell Canyon and d01 for the rest
+    #[arg(long, env = "YOSE_BLOCKED")]
+    pub blocked: bool,
+
+    /// Entry trailheads and start dates
+    #[command(flatten)]
+    pub selection: Selection,
+}
+
+/// The Donohue Pass exit quotas. The trailhead listing has them as d01 (wpsName DonohueValley)
+/// and d02 (DonohueLyell), with a quota but no region.
+pub const DONOHUE_EXITS: [&str; 2] = ["d01", "d02"];
+
+/// The region whose report is assumed to carry the Donohue Pass exits. Nothing upstream says
+/// which report does, but jm is the region for trips out over Donohue Pass: each of its
+/// listings sends hikers who "do not plan on exiting the park via Donohue Pass" to the same
+/// trailhead in yv or tm. `check_donohue_report` fails when the report doesn't carry them.
+pub const DONOHUE_REGION: &str = "jm";
+
+/// The Donohue Pass exit quota a John Muir Trail entry draws from. The listing doesn't say,
+/// so this is assumed from the quotas' wpsNames: Lyell Canyon (j24b) draws on DonohueLyell and
+/// the Valley entries on DonohueValley. Sunrise Lakes (j19) fits neither name and is assumed
+/// to draw on DonohueValley too.
+pub fn donohue_exit(trailhead: &Trailhead) -> Option<&'static str> {
+    match (trailhead.region.as_deref(), trailhead.id.as_str()) {
+        (Some(DONOHUE_REGION), "j24b") => Some("d02"),
+        (Some(DONOHUE_REGION), _) => Some("d01"),
+        _ => None,
+    }
+}
+
+pub fn is_donohue_exit(trailhead: &Trailhead) -> bool {
+    DONOHUE_EXITS.contains(&trailhead.id.as_str())
+}
+
+/// Checks that the `DONOHUE_REGION` report, `None` when it failed to load, carries a Donohue
+/// Pass exit. Without one every John Muir Trail entry would look blocked.
+pub fn check_donohue_report(report: Option<&[ReportDate]>) -> Result<(), String> {
+    let report = report.ok_or_else(|| {
+        format!(
+            "--donohue needs the {} report, which failed to load",
+            DONOHUE_REGION
+        )
+    })?;
+
+    let carried = report.iter().any(|date| {
+        let mut ids = date.values.keys();
+        ids.any(|id| DONOHUE_EXITS.contains(&id.as_str()))
+    });
+    if !carried {
+        return Err(format!(
+            "the {} report has no Donohue Pass exits ({}) for --donohue to check",
+            DONOHUE_REGION,
+            DONOHUE_EXITS.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// A start date at an entry trailhead, with the exit trailhead open on the last day when
+/// exits were asked for, and the Donohue Pass exit open on the first day when it was.
+#[derive(Debug, Clone)]
+pub struct Trip<'a> {
+    pub entry: AvailabilityRow<'a>,
+    pub end: NaiveDate,
+    pub exit: Option<AvailabilityRow<'a>>,
+    pub donohue: Option<AvailabilityRow<'a>>,
+    /// Quotas without enough spots, only kept with --blocked
+    pub blocked: Vec<Constraint<'a>>,
+}
+
+/// A quota a trip needs spots on besides its entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constraint<'a> {
+    /// None of the exit trailheads on the last day
+    Exit(NaiveDate),
+    /// A Donohue Pass exit quota on the first day
+    Donohue(&'a str, NaiveDate),
+}
+
+impl fmt::Display for Constraint<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Exit(date) => write!(f, "exit on {}", date),
+            Constraint::Donohue(id, date) => write!(f, "donohue {} on {}", id, date),
+        }
+    }
+}
+
+/// Exits with enough spots, looked up before the selection narrows the rows down to entries.
+#[derive(Debug, Default)]
+pub struct Exits<'a> {
+    /// The most open exit trailhead per date
+    trailheads: BTreeMap<NaiveDate, AvailabilityRow<'a>>,
+    /// Each Donohue Pass exit quota per date
+    donohue: BTreeMap<(NaiveDate, &'a str), AvailabilityRow<'a>>,
+}
+
+impl Trips {
+    pub fn is_exit(&self, trailhead: &Trailhead) -> bool {
+        self.exit
+            .iter()
+            .any(|name| fuzzy::matches(name, &trailhead.name))
+    }
+
+    /// Whether the trailhead is an entry or an exit the trips need.
+    pub fn includes(&self, trailhead: &Trailhead) -> bool {
+        let entry = self.selection.includes(trailhead)
+            && (!self.donohue || donohue_exit(trailhead).is_some());
+        entry || self.is_exit(trailhead) || (self.donohue && is_donohue_exit(trailhead))
+    }
+
+    /// Exits only need the same number of spots as the entry, the rest of the selection
+    /// applies to the start of the trip.
+    pub fn exits<'a>(&self, rows: &[AvailabilityRow<'a>]) -> Exits<'a> {
+        let min_availability = self.selection.min_availability.unwrap_or(1);
+        let mut exits = Exits::default();
+
+        for row in rows {
+            if row.availability < min_availability {
+                continue;
+            }
+
+            if is_donohue_exit(row.trailhead) {
+                let id = row.trailhead.id.as_str();
+                exits.donohue.insert((row.date, id), *row);
+            }
+            if self.is_exit(row.trailhead) {
+                let best = exits.trailheads.entry(row.date).or_insert(*row);
+                if row.availability > best.availability {
+                    *best = *row;
+                }
+            }
+        }
+
+        exits
+    }
+
+    /// Every open entry that starts a trip, in the order of `entries`. Entries failing another
+    /// quota are only kept with --blocked.
+    pub fn trips<'a>(
+        &self,
+        entries: impl IntoIterator<Item = AvailabilityRow<'a>>,
+        exits: &Exits<'a>,
+    ) -> Vec<Trip<'a>> {
+        entries
+            .into_iter()
+            .filter(|entry| entry.availability > 0)
+            .filter_map(|entry| {
+                let end = entry.date + Duration::days(i64::from(self.nights));
+                let mut blocked = Vec::new();
+
+                let exit = if self.exit.is_empty() {
+                    None
+                } else {
+                    let exit = exits.trailheads.get(&end).copied();
+                    if exit.is_none() {
+                        blocked.push(Constraint::Exit(end));
+                    }
+                    exit
+                };
+                // assumed: the exit quota is counted on the day the permit starts, like the entry
+                // quota, nothing upstream says which day it is charged on
+                let donohue = if self.donohue {
+                    let id = donohue_exit(entry.trailhead)?;
+                    let donohue = exits.donohue.get(&(entry.date, id)).copied();
+                    if donohue.is_none() {
+                        blocked.push(Constraint::Donohue(id, entry.date));
+                    }
+                    donohue
+                } else {
+                    None
+                };
+
+                if !blocked.is_empty() && !self.blocked {
+                    return None;
+                }
+                Some(Trip {
+                    entry,
+                    end,
+                    exit,
+                    donohue,
+                    blocked,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::availability::AvailabilityRow;
+    use crate::trips::{check_donohue_report, donohue_exit, Trips};
+    use crate::{ReportDate, Selection, Trailhead};
+    use chrono::NaiveDate;
+
+    fn trips(nights: u32, exit: &[&str]) -> Trips {
+        Trips {
+            nights,
+            exit: exit.iter().map(|name| name.to_string()).collect(),
+            donohue: false,
+            blocked: false,
+            selection: Selection::default(),
+        }
+    }
+
+    #[test]
+    fn start_dates() {
+        let lyell = Trailhead::test("t24b", "Lyell Canyon", "tm");
+        let cathedral = Trailhead::test("t21", "Cathedral Lakes", "tm");
+        let glen_aulin = Trailhead::test("t22", "Glen Aulin", "tm");
+        let day = |day| NaiveDate::from_ymd_opt(2020, 9, day).unwrap();
+        let entries = vec![
+            AvailabilityRow::new(day(10), &lyell, 2),
+            AvailabilityRow::new(day(11), &lyell, 0),
+            AvailabilityRow::new(day(12), &lyell, 4),
+        ];
+        let rows = vec![
+            AvailabilityRow::new(day(13), &cathedral, 1),
+            AvailabilityRow::new(day(13), &glen_aulin, 3),
+            AvailabilityRow::new(day(15), &cathedral, 0),
+        ];
+
+        let any_exit = trips(3, &[]);
+        let starts = any_exit
+            .trips(entries.clone(), &any_exit.exits(&rows))
+            .iter()
+            .map(|trip| (trip.entry.date, trip.end))
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![(day(10), day(13)), (day(12), day(15))]);
+
+        let loops = trips(3, &["cathedral", "glen"]);
+        let result = loops.trips(entries, &loops.exits(&rows));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].entry.date, day(10));
+        assert_eq!(
+            result[0].exit.map(|exit| exit.trailhead.id.as_str()),
+            Some("t22")
+        );
+    }
+
+    #[test]
+    fn donohue_exit_quota() {
+        let lyell = Trailhead::test("j24b", "Lyell Canyon", "jm");
+        let happy_isles = Trailhead::test("j01b", "Happy Isles to Little Yosemite Valley", "jm");
+        let valley = Trailhead {
+            region: None,
+            ..Trailhead::test("d01", "Donohue Exit 1", "")
+        };
+        let lyell_exit = Trailhead {
+            region: None,
+            ..Trailhead::test("d02", "Donohue Exit 2", "")
+        };
+        assert_eq!(donohue_exit(&lyell), Some("d02"));
+        assert_eq!(donohue_exit(&happy_isles), Some("d01"));
+        assert_eq!(
+            donohue_exit(&Trailhead::test("t24b", "Lyell Canyon", "tm")),
+            None
+        );
+
+        let day = |day| NaiveDate::from_ymd_opt(2020, 9, day).unwrap();
+        let entries = vec![
+            AvailabilityRow::new(day(10), &lyell, 2),
+            AvailabilityRow::new(day(10), &happy_isles, 2),
+            AvailabilityRow::new(day(11), &lyell, 2),
+        ];
+        let rows = vec![
+            AvailabilityRow::new(day(10), &valley, 0),
+            AvailabilityRow::new(day(10), &lyell_exit, 3),
+            AvailabilityRow::new(day(11), &lyell_exit, 0),
+        ];
+
+        let mut jmt = trips(14, &[]);
+        jmt.donohue = true;
+        let result = jmt.trips(entries.clone(), &jmt.exits(&rows));
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].entry.trailhead.id, "j24b");
+        assert_eq!(result[0].entry.date, day(10));
+        assert_eq!(result[0].donohue.map(|exit| exit.availability), Some(3));
+    }
+
+    #[test]
+    fn name_blocking_quotas() {
+        let lyell = Trailhead::test("j24b", "Lyell Canyon", "jm");
+        let sunrise = Trailhead::test("j19", "Sunrise Lakes", "jm");
+        let valley_exit = Trailhead {
+            region: None,
+            ..Trailhead::test("d01", "Donohue Exit 1", "")
+        };
+        let lyell_exit = Trailhead {
+            region: None,
+            ..Trailhead::test("d02", "Donohue Exit 2", "")
+        };
+        let glen_aulin = Trailhead::test("t22", "Glen Aulin", "tm");
+        let day = |day| NaiveDate::from_ymd_opt(2020, 9, day).unwrap();
+        let entries = vec![
+            AvailabilityRow::new(day(10), &lyell, 2),
+            AvailabilityRow::new(day(10), &sunrise, 2),
+            AvailabilityRow::new(day(11), &lyell, 2),
+            AvailabilityRow::new(day(11), &sunrise, 2),
+        ];
+        // Sunrise Lakes draws on DonohueValley, full on the 10th
+        let rows = vec![
+            AvailabilityRow::new(day(10), &lyell_exit, 3),
+            AvailabilityRow::new(day(10), &valley_exit, 0),
+            AvailabilityRow::new(day(11), &valley_exit, 4),
+            AvailabilityRow::new(day(14), &glen_aulin, 1),
+        ];
+
+        let mut jmt = trips(3, &["glen"]);
+        jmt.donohue = true;
+        jmt.blocked = true;
+        let blocked = jmt
+            .trips(entries, &jmt.exits(&rows))
+            .iter()
+            .map(|trip| {
+                let blocked = trip.blocked.iter().map(ToString::to_string);
+                (
+                    trip.entry.trailhead.id.as_str(),
+                    blocked.collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            blocked,
+            vec![
+                ("j24b", vec!["exit on 2020-09-13".to_string()]),
+                (
+                    "j19",
+                    vec![
+                        "exit on 2020-09-13".to_string(),
+                        "donohue d01 on 2020-09-10".to_string()
+                    ]
+                ),
+                ("j24b", vec!["donohue d02 on 2020-09-11".to_string()]),
+                ("j19", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn require_donohue_exits_in_report() {
+        let report = |ids: &[&str]| ReportDate {
+            date: NaiveDate::from_ymd_opt(2020, 9, 10).unwrap(),
+            values: ids.iter().map(|id| (id.to_string(), 2)).collect(),
+        };
+
+        assert!(check_donohue_report(Some(&[report(&["j24b", "d02"])])).is_ok());
+        assert_eq!(
+            check_donohue_report(Some(&[report(&["j24b", "j19"])])),
+            Err("the jm report has no Donohue Pass exits (d01, d02) for --donohue to check".into())
+        );
+        assert!(check_donohue_report(Some(&[])).is_err());
+        assert_eq!(
+            check_donohue_report(None),
+            Err("--donohue needs the jm report, which failed to load".into())
+        );
+    }
+}